@@ -0,0 +1,4 @@
+// times (recr 1996) with utils::time::bench: cargo run --release --example bench
+fn main() {
+    nova::run_bench();
+}
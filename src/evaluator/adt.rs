@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::constants::*;
+use crate::message::*;
+use crate::parser::parse_node::*;
+
+use super::context_tco::{Context, EvalContext};
+use super::data_tco::*;
+use super::evaluator_tco::*;
+use super::function_tco::{arity_error, Function};
+use super::params::Params;
+
+// a value made by one of the constructors of a data declaration
+pub struct AdtData {
+    pub type_name: String,
+    pub ctor: String,
+    pub fields: Vec<DataValue>,
+}
+
+impl AdtData {
+    // same constructor of the same type, with equal fields
+    pub fn equals(&self, other: &AdtData) -> bool {
+        self.type_name.eq(&other.type_name)
+            && self.ctor.eq(&other.ctor)
+            && self.fields.len() == other.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(other.fields.iter())
+                .all(|(l, r)| l.equals(r))
+    }
+}
+
+// written the way it is built: Empty, (Circle 2)
+impl fmt::Display for AdtData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fields.is_empty() {
+            return write!(f, "{}", self.ctor);
+        }
+
+        let fields: Vec<String> = self.fields.iter().map(|f| f.to_nested_string()).collect();
+        write!(
+            f,
+            "{}{} {}{}",
+            OPEN_EXPR,
+            self.ctor,
+            fields.join(SPACE),
+            CLOSE_EXPR
+        )
+    }
+}
+
+// constructor with fields, curried like a builtin: (Rect 1) waits for the height
+#[derive(Clone)]
+pub struct Constructor {
+    type_name: String,
+    name: String,
+    params: Params,
+}
+
+impl Function for Constructor {
+    fn apply(&self, args: &[Arg]) -> Rc<dyn Function> {
+        Rc::new(Constructor {
+            type_name: self.type_name.clone(),
+            name: self.name.clone(),
+            params: self.params.apply(args),
+        })
+    }
+
+    fn execute(&self, args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+        let adt = AdtData {
+            type_name: self.type_name.clone(),
+            ctor: self.name.clone(),
+            fields: Arg::expect_all_eval(args)?,
+        };
+        Ok(EvaluatedExpr(AdtValue(Rc::new(adt))))
+    }
+
+    fn resolve(&self, context: &EvalContext) -> Result<Expression> {
+        let fin = self.params.get_finite().unwrap();
+        match fin.params_diff() {
+            Ordering::Less => Ok(EvaluatedExpr(FunctionVariable(Rc::new(self.clone())))),
+            Ordering::Equal => self.execute(&fin.received_args, context),
+            Ordering::Greater => Err(arity_error(
+                &self.name,
+                fin.params.len(),
+                fin.received_args.len(),
+            )),
+        }
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_num_fields(&self) -> Option<usize> {
+        self.params.get_finite().map(|fin| fin.params.len())
+    }
+
+    fn to_string(&self) -> String {
+        format!("<constructor '{}'>", self.name)
+    }
+}
+
+// constructors with fields are functions, the rest are values
+pub fn data_bindings(data_def: &DataDef) -> EvalContext {
+    let mut bound = Context::new();
+
+    for (name, fields) in data_def.ctors.iter() {
+        if fields.is_empty() {
+            let adt = AdtData {
+                type_name: data_def.name.clone(),
+                ctor: name.clone(),
+                fields: vec![],
+            };
+            bound.add_variable(name, AdtValue(Rc::new(adt)));
+            continue;
+        }
+
+        let ctor = Constructor {
+            type_name: data_def.name.clone(),
+            name: name.clone(),
+            params: Params::new_finite(fields.iter().map(|f| f.as_str()).collect()),
+        };
+        bound.add_function(name, Rc::new(ctor));
+    }
+
+    EvalContext::new_from_context(&bound)
+}
+
+// Shape => Circle(r) | Rect(w,h) | Empty
+fn describe(data_def: &DataDef) -> String {
+    let ctors: Vec<String> = data_def
+        .ctors
+        .iter()
+        .map(|(name, fields)| match fields.is_empty() {
+            true => name.clone(),
+            false => format!(
+                "{}{}{}{}",
+                name,
+                OPEN_EXPR,
+                fields.join(VAR_SEP),
+                CLOSE_EXPR
+            ),
+        })
+        .collect();
+
+    format!("{} => {}", data_def.name, ctors.join(" | "))
+}
+
+// for (data ...): only a top level declaration gets here, the ones in function bodies
+// are added to the body's ctx like defs
+pub fn resolve_data_node(data_def: &DataDef) -> Result<DataValue> {
+    if !data_def.global {
+        let msg = format!(
+            "'{}' can only be used at the top level or as a statement in a function body.",
+            DATA_NAME
+        );
+        return err!(msg);
+    }
+
+    let value = Str(describe(data_def));
+    Ok(SetVar(LetReturn::new(data_bindings(data_def), value)))
+}
+
+fn literal_value(pattern: &ASTNode) -> Option<DataValue> {
+    match &pattern.value {
+        Number(n) => Some(Num(*n)),
+        BigNumber(big) => Some(BigNum(Rc::clone(big))),
+        Fraction(ratio) => Some(Ratio(Rc::clone(ratio))),
+        Decimal(float) => Some(Float(*float)),
+        _ => None,
+    }
+}
+
+fn match_all(
+    ctx: &EvalContext,
+    patterns: &[Rc<ASTNode>],
+    values: &[DataValue],
+    bindings: &mut Vec<(String, DataValue)>,
+) -> Result<bool> {
+    for (pattern, value) in patterns.iter().zip(values.iter()) {
+        if !match_pattern(ctx, pattern, value, bindings)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// (cons head tail) matches a list or lazy list that isn't empty
+fn match_cons(
+    ctx: &EvalContext,
+    patterns: &[Rc<ASTNode>],
+    value: &DataValue,
+    bindings: &mut Vec<(String, DataValue)>,
+) -> Result<bool> {
+    let (head, tail) = match value {
+        ListValue(lst) if !lst.is_empty() => {
+            (lst[0].clone(), ListValue(Rc::new(lst[1..].to_vec())))
+        }
+        LazyList(lazy) => (lazy.head.clone(), lazy.force_tail()?),
+        _ => return Ok(false),
+    };
+
+    match_all(ctx, patterns, &[head, tail], bindings)
+}
+
+// adds the names the pattern binds to bindings. a symbol bound to a constructor without
+// fields is compared like a literal, one with fields needs them in the pattern, and any
+// other symbol is a name for the value
+pub fn match_pattern(
+    ctx: &EvalContext,
+    pattern: &ASTNode,
+    value: &DataValue,
+    bindings: &mut Vec<(String, DataValue)>,
+) -> Result<bool> {
+    let matched = match (&pattern.value, value) {
+        (Symbol(sym), _) if sym.eq(WILDCARD) => true,
+        (Symbol(sym), _) => match ctx.get(sym) {
+            Some(AdtValue(adt)) if adt.ctor.eq(sym) && adt.fields.is_empty() => {
+                value.equals(&AdtValue(adt))
+            }
+            Some(FunctionVariable(f)) if f.get_name().eq(sym) && f.get_num_fields().is_some() => {
+                let fields = f.get_num_fields().unwrap();
+                return errf!(
+                    "'{}' has {} fields but the pattern '{}' has 0.",
+                    sym,
+                    fields,
+                    pattern
+                );
+            }
+            _ => {
+                bindings.push((sym.clone(), value.clone()));
+                true
+            }
+        },
+        (Number(_) | BigNumber(_) | Fraction(_) | Decimal(_), _) => {
+            value.num_equals(&literal_value(pattern).unwrap())
+        }
+        (Boolean(b), Bool(other)) => b == other,
+        (StringLiteral(string), Str(other)) => string.eq(other),
+        (List(patterns), ListValue(lst)) => {
+            patterns.len() == lst.len() && match_all(ctx, patterns, lst, bindings)?
+        }
+        (ParseExpression(children), _) => {
+            let name = children[0].get_symbol().unwrap();
+            if name.eq(CONS) {
+                return match_cons(ctx, &children[1..], value, bindings);
+            }
+
+            match value {
+                AdtValue(adt) if adt.ctor.eq(&name) => {
+                    if adt.fields.len() != children.len() - 1 {
+                        return errf!(
+                            "'{}' has {} fields but the pattern '{}' has {}.",
+                            name,
+                            adt.fields.len(),
+                            pattern,
+                            children.len() - 1
+                        );
+                    }
+                    match_all(ctx, &children[1..], &adt.fields, bindings)?
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+
+    Ok(matched)
+}
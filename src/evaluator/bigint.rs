@@ -0,0 +1,536 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+// integer of any size, for results that don't fit in NumType
+// magnitude is base 2^32, least significant limb first, without leading zero limbs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool, // false for zero
+    mag: Vec<u32>,
+}
+
+const LIMB_BITS: u32 = 32;
+const DECIMAL_CHUNK: u32 = 1_000_000_000; // 9 digits at a time for parse and to_string
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt {
+            negative: false,
+            mag: vec![],
+        }
+    }
+
+    pub fn from_i64(n: i64) -> BigInt {
+        let abs = n.unsigned_abs();
+        BigInt::from_mag(n < 0, vec![abs as u32, (abs >> LIMB_BITS) as u32])
+    }
+
+    // drops the fraction, None for inf and NaN
+    pub fn from_f64(float: f64) -> Option<BigInt> {
+        if !float.is_finite() {
+            return None;
+        }
+
+        // exact: the float is a whole number and dividing by 2^32 only changes the exponent
+        let mut abs = float.abs().trunc();
+        let mut mag = vec![];
+        while abs >= 1.0 {
+            let limb = abs % 4294967296.0;
+            mag.push(limb as u32);
+            abs = (abs - limb) / 4294967296.0;
+        }
+
+        Some(BigInt::from_mag(float < 0.0, mag))
+    }
+
+    fn from_mag(negative: bool, mut mag: Vec<u32>) -> BigInt {
+        while mag.last() == Some(&0) {
+            mag.pop();
+        }
+
+        BigInt {
+            negative: negative && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    // None if it doesn't fit
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.mag.len() > 2 {
+            return None;
+        }
+
+        let low = *self.mag.first().unwrap_or(&0) as u64;
+        let high = *self.mag.get(1).unwrap_or(&0) as u64;
+        let abs = (high << LIMB_BITS) | low;
+
+        if self.negative {
+            0i64.checked_sub_unsigned(abs)
+        } else {
+            i64::try_from(abs).ok()
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn neg(&self) -> BigInt {
+        BigInt::from_mag(!self.negative, self.mag.clone())
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_mag(false, self.mag.clone())
+    }
+
+    pub fn add(&self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_mag(self.negative, add_mag(&self.mag, &other.mag));
+        }
+
+        // different signs: the sign of the bigger magnitude wins
+        match cmp_mag(&self.mag, &other.mag) {
+            Ordering::Less => BigInt::from_mag(other.negative, sub_mag(&other.mag, &self.mag)),
+            _ => BigInt::from_mag(self.negative, sub_mag(&self.mag, &other.mag)),
+        }
+    }
+
+    pub fn sub(&self, other: &BigInt) -> BigInt {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &BigInt) -> BigInt {
+        let negative = self.negative != other.negative;
+        BigInt::from_mag(negative, mul_mag(&self.mag, &other.mag))
+    }
+
+    // rounds towards zero like i64 / and %, None when dividing by zero
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quot, rem) = div_rem_mag(&self.mag, &other.mag);
+        let quot = BigInt::from_mag(self.negative != other.negative, quot);
+        let rem = BigInt::from_mag(self.negative, rem);
+        Some((quot, rem))
+    }
+
+    // rounds down, so the remainder has the sign of the divisor
+    pub fn div_mod_floor(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        let (quot, rem) = self.div_rem(other)?;
+
+        if !rem.is_zero() && rem.negative != other.negative {
+            let one = BigInt::from_i64(1);
+            return Some((quot.sub(&one), rem.add(other)));
+        }
+
+        Some((quot, rem))
+    }
+
+    // bits in the magnitude, 0 for zero
+    pub fn bits(&self) -> u64 {
+        match self.mag.last() {
+            Some(top) => {
+                (self.mag.len() as u64 - 1) * LIMB_BITS as u64
+                    + (LIMB_BITS - top.leading_zeros()) as u64
+            }
+            None => 0,
+        }
+    }
+
+    // self * 2^amount, moving whole limbs then the remaining bits
+    pub fn shl(&self, amount: u64) -> BigInt {
+        if self.is_zero() {
+            return BigInt::zero();
+        }
+
+        let bits = amount % LIMB_BITS as u64;
+        let mut mag = vec![0u32; (amount / LIMB_BITS as u64) as usize];
+        let mut carry = 0u32;
+        for limb in self.mag.iter() {
+            let shifted = (*limb as u64) << bits;
+            mag.push(shifted as u32 | carry);
+            carry = (shifted >> LIMB_BITS) as u32;
+        }
+        mag.push(carry);
+
+        BigInt::from_mag(self.negative, mag)
+    }
+
+    // floor(self / 2^amount) like an arithmetic shift: -5 >> 1 is -3
+    pub fn shr(&self, amount: u64) -> BigInt {
+        let limbs = (amount / LIMB_BITS as u64).min(self.mag.len() as u64) as usize;
+        let bits = amount % LIMB_BITS as u64;
+        let (dropped, kept) = self.mag.split_at(limbs);
+
+        let mut mag = Vec::with_capacity(kept.len());
+        for (i, limb) in kept.iter().enumerate() {
+            let high = kept
+                .get(i + 1)
+                .map_or(0, |next| ((*next as u64) << LIMB_BITS >> bits) as u32);
+            mag.push((limb >> bits) | high);
+        }
+
+        let shifted = BigInt::from_mag(self.negative, mag);
+        let low_bits = kept
+            .first()
+            .map_or(0, |low| low & ((1u64 << bits) - 1) as u32);
+        // a negative number with anything shifted out rounds down
+        if self.negative && (low_bits != 0 || dropped.iter().any(|limb| *limb != 0)) {
+            return shifted.sub(&BigInt::from_i64(1));
+        }
+        shifted
+    }
+
+    // and, or and xor on the two's complement like i64, with the sign extended forever:
+    // (band -1 x) is x
+    pub fn bitwise(&self, other: &BigInt, op: fn(u32, u32) -> u32) -> BigInt {
+        // one more limb than either needs, so the top one is all sign
+        let len = self.mag.len().max(other.mag.len()) + 1;
+        let left = self.twos_complement(len);
+        let right = other.twos_complement(len);
+
+        let limbs: Vec<u32> = left
+            .iter()
+            .zip(right.iter())
+            .map(|(l, r)| op(*l, *r))
+            .collect();
+        let negative = limbs[len - 1] >> (LIMB_BITS - 1) == 1;
+        let mag = if negative { negate_limbs(limbs) } else { limbs };
+        BigInt::from_mag(negative, mag)
+    }
+
+    fn twos_complement(&self, len: usize) -> Vec<u32> {
+        let mut limbs = self.mag.clone();
+        limbs.resize(len, 0);
+        if self.negative {
+            return negate_limbs(limbs);
+        }
+        limbs
+    }
+
+    // never negative, gcd(0, 0) is 0
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while !b.is_zero() {
+            let (_, rem) = a.div_rem(&b).unwrap();
+            a = b;
+            b = rem;
+        }
+        a
+    }
+
+    // decimal digits with an optional leading -
+    pub fn parse(string: &str) -> Option<BigInt> {
+        let (negative, digits) = match string.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, string),
+        };
+
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut mag: Vec<u32> = vec![];
+        let first = digits.len() % 9;
+        let chunks = std::iter::once(&digits[..first])
+            .filter(|chunk| !chunk.is_empty())
+            .chain(
+                digits.as_bytes()[first..]
+                    .chunks(9)
+                    .map(|c| std::str::from_utf8(c).unwrap()),
+            );
+
+        for chunk in chunks {
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_small_add(&mut mag, scale, chunk.parse::<u32>().unwrap());
+        }
+
+        Some(BigInt::from_mag(negative, mag))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let abs = self
+            .mag
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+
+        if self.negative {
+            -abs
+        } else {
+            abs
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &other.mag),
+            (true, true) => cmp_mag(&other.mag, &self.mag),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // least significant chunk first
+        let mut chunks: Vec<u32> = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (quot, rem) = div_rem_small(&mag, DECIMAL_CHUNK);
+            chunks.push(rem);
+            mag = quot;
+        }
+
+        let mut string = String::new();
+        if self.negative {
+            string.push('-');
+        }
+
+        let mut chunks = chunks.iter().rev();
+        string.push_str(&chunks.next().unwrap().to_string());
+        for chunk in chunks {
+            string.push_str(&format!("{:09}", chunk));
+        }
+
+        write!(f, "{}", string)
+    }
+}
+
+fn trim(mut mag: Vec<u32>) -> Vec<u32> {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+    mag
+}
+
+// -x in two's complement: invert and add one
+fn negate_limbs(mut limbs: Vec<u32>) -> Vec<u32> {
+    let mut carry = 1u64;
+    for limb in limbs.iter_mut() {
+        let sum = !*limb as u64 + carry;
+        *limb = sum as u32;
+        carry = sum >> LIMB_BITS;
+    }
+    limbs
+}
+
+fn cmp_mag(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_mag(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..left.len().max(right.len()) {
+        let total = *left.get(i).unwrap_or(&0) as u64 + *right.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(total as u32);
+        carry = total >> LIMB_BITS;
+    }
+
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// left >= right
+fn sub_mag(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut diff = Vec::with_capacity(left.len());
+    let mut borrow = 0i64;
+
+    for (i, limb) in left.iter().enumerate() {
+        let mut total = *limb as i64 - *right.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if total < 0 {
+            total += 1 << LIMB_BITS;
+            borrow = 1;
+        }
+        diff.push(total as u32);
+    }
+
+    trim(diff)
+}
+
+fn mul_mag(left: &[u32], right: &[u32]) -> Vec<u32> {
+    if left.is_empty() || right.is_empty() {
+        return vec![];
+    }
+
+    let mut product = vec![0u32; left.len() + right.len()];
+    for (i, l) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, r) in right.iter().enumerate() {
+            let total = *l as u64 * *r as u64 + product[i + j] as u64 + carry;
+            product[i + j] = total as u32;
+            carry = total >> LIMB_BITS;
+        }
+        product[i + right.len()] = carry as u32;
+    }
+
+    trim(product)
+}
+
+// mag = mag * factor + add
+fn mul_small_add(mag: &mut Vec<u32>, factor: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in mag.iter_mut() {
+        let total = *limb as u64 * factor as u64 + carry;
+        *limb = total as u32;
+        carry = total >> LIMB_BITS;
+    }
+
+    if carry > 0 {
+        mag.push(carry as u32);
+    }
+}
+
+fn div_rem_small(mag: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quot = vec![0u32; mag.len()];
+    let mut rem = 0u64;
+
+    for i in (0..mag.len()).rev() {
+        let current = (rem << LIMB_BITS) | mag[i] as u64;
+        quot[i] = (current / divisor as u64) as u32;
+        rem = current % divisor as u64;
+    }
+
+    (trim(quot), rem as u32)
+}
+
+// shift and subtract one bit at a time: slow for huge numbers but simple
+fn div_rem_mag(left: &[u32], right: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if right.len() == 1 {
+        let (quot, rem) = div_rem_small(left, right[0]);
+        return (quot, trim(vec![rem]));
+    }
+
+    if cmp_mag(left, right) == Ordering::Less {
+        return (vec![], left.to_vec());
+    }
+
+    let mut quot = vec![0u32; left.len()];
+    let mut rem: Vec<u32> = vec![];
+
+    for bit in (0..left.len() * LIMB_BITS as usize).rev() {
+        let limb = bit / LIMB_BITS as usize;
+        let shift = bit % LIMB_BITS as usize;
+
+        // rem = rem * 2 + next bit of left
+        mul_small_add(&mut rem, 2, (left[limb] >> shift) & 1);
+
+        if cmp_mag(&rem, right) != Ordering::Less {
+            rem = sub_mag(&rem, right);
+            quot[limb] |= 1 << shift;
+        }
+    }
+
+    (trim(quot), rem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(string: &str) -> BigInt {
+        BigInt::parse(string).unwrap()
+    }
+
+    #[test]
+    fn bigint_test() {
+        let max = BigInt::from_i64(i64::MAX);
+        let one = BigInt::from_i64(1);
+
+        assert_eq!(max.add(&one).to_string(), "9223372036854775808");
+        assert_eq!(max.add(&one).to_i64(), None);
+        assert_eq!(BigInt::from_i64(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!(BigInt::from_i64(-5).to_string(), "-5");
+
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+        assert_eq!(a.add(&b).to_string(), "-864197532086419753208641975320");
+        assert_eq!(a.sub(&b).to_string(), "1111111110111111111011111111100");
+        assert_eq!(
+            a.mul(&b).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+        assert_eq!(a.sub(&a), BigInt::zero());
+
+        let (q, r) = b.div_rem(&a).unwrap();
+        assert_eq!(
+            (q.to_string(), r.to_string()),
+            ("-8".to_string(), "-9000000000900000000090".to_string())
+        );
+
+        let (q, r) = b.div_mod_floor(&a).unwrap();
+        assert_eq!(q.to_string(), "-9");
+        assert_eq!(r.to_string(), "123456780012345678001234567800");
+        assert!(a.div_rem(&BigInt::zero()).is_none());
+
+        assert!(b < a);
+        assert!(big("-2") < big("-1"));
+        assert_eq!(big("-0"), BigInt::zero());
+        assert!(BigInt::parse("12a").is_none());
+
+        assert_eq!(
+            BigInt::from_f64(-1e19).unwrap().to_string(),
+            "-10000000000000000000"
+        );
+        assert_eq!(BigInt::from_f64(2.9).unwrap().to_i64(), Some(2));
+        assert!(BigInt::from_f64(f64::NAN).is_none());
+
+        assert_eq!(big("-12").gcd(&big("18")).to_string(), "6");
+        assert_eq!(a.gcd(&BigInt::zero()), a);
+
+        assert_eq!(
+            BigInt::from_i64(1).shl(70).to_string(),
+            "1180591620717411303424"
+        );
+        assert_eq!(big("-3").shl(33).to_string(), "-25769803776");
+        assert_eq!(a.shl(100).shr(100), a);
+        assert_eq!(big("-5").shr(1).to_string(), "-3");
+        assert_eq!(big("-4").shr(1).to_string(), "-2");
+        assert_eq!(b.shr(1000).to_string(), "-1");
+        assert_eq!(a.shr(1000), BigInt::zero());
+        assert_eq!(
+            big("-18446744073709551616").shr(32).to_string(),
+            "-4294967296"
+        );
+        let and = |x: &str, y: &str| big(x).bitwise(&big(y), |l, r| l & r).to_string();
+        assert_eq!(
+            and("-1", "123456789012345678901234567890"),
+            "123456789012345678901234567890"
+        );
+        assert_eq!(
+            and("-18446744073709551616", "18446744073709551617"),
+            "18446744073709551616"
+        );
+        assert_eq!(and("-5", "-3"), "-7");
+        assert_eq!(big("-5").bitwise(&big("3"), |l, r| l | r).to_string(), "-5");
+        assert_eq!(big("-1").bitwise(&a, |l, r| l ^ r), a.neg().sub(&one));
+        assert_eq!(a.bits(), 97);
+        assert_eq!(BigInt::zero().bits(), 0);
+    }
+}
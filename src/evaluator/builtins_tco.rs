@@ -1,15 +1,21 @@
 use std::cmp::Ordering;
+use std::io::Write;
 use std::rc;
 use std::rc::Rc;
 
 use crate::constants::*;
 use crate::message::*;
 
+use super::bigint::BigInt;
 use super::context_tco::*;
 use super::data_tco::*;
+use super::eval_helpers_tco::next_short_circuit;
 use super::evaluator_tco::*;
 use super::function_tco::*;
+use super::map::MapData;
+use super::number::{parse_float, Number};
 use super::params::Params;
+use super::rational::Rational;
 
 macro_rules! name {
     ($name:expr) => {
@@ -27,13 +33,7 @@ macro_rules! ev {
 macro_rules! check {
     ($name:expr, $num:expr, $args:expr) => {
         if $args.len() != $num {
-            let msg = format!(
-                "'{}' expected {} arguments but received {}.",
-                $name,
-                $num,
-                $args.len()
-            );
-            return err!(msg);
+            return Err(arity_error($name, $num, $args.len()));
         }
     };
 }
@@ -44,9 +44,9 @@ macro_rules! unit {
     };
 }
 
-fn get_nums(args: &[Arg]) -> Result<Vec<NumType>> {
-    let r: Result<Vec<NumType>> =
-        Arg::expect_all_eval(args).and_then(|f| f.into_iter().map(|x| x.expect_num()).collect());
+pub fn get_nums(args: &[Arg]) -> Result<Vec<Number>> {
+    let r: Result<Vec<Number>> =
+        Arg::expect_all_eval(args).and_then(|f| f.iter().map(Number::from_value).collect());
     return r;
 }
 
@@ -92,15 +92,11 @@ impl Function for BuiltIn {
                 // when less return curried function
                 Ordering::Less => Ok(EvaluatedExpr(FunctionVariable(Rc::new(self.clone())))),
                 Ordering::Equal => self.execute(&fin.received_args, context),
-                Ordering::Greater => {
-                    let msg = format!(
-                        "'{}' expected {} arguments but received {}.",
-                        self.name,
-                        fin.params.len(),
-                        fin.received_args.len()
-                    );
-                    err!(msg)
-                }
+                Ordering::Greater => Err(arity_error(
+                    &self.name,
+                    fin.params.len(),
+                    fin.received_args.len(),
+                )),
             },
             Params::Infinite(inf) => {
                 if inf.received_args.len() < inf.min {
@@ -116,6 +112,10 @@ impl Function for BuiltIn {
         self.arg_type.clone()
     }
 
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
     fn to_string(&self) -> String {
         // format!("<function '{}' len:{}, recv:{}>", self.name, self.params.clone().received_args().len(),self.params.to_string())
         format!("<function '{}'>", self.name)
@@ -174,26 +174,27 @@ impl BuiltInBuilder {
 }
 
 // (((add 1) 2) 3 4)
+// overflow goes to BigNum instead of wrapping
 fn add(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
     let r = get_nums(args);
-    let total: Result<NumType> = r.map(|v| v.into_iter().sum());
+    let total: Result<Number> = r.map(|v| v.iter().fold(Number::Int(0), |acc, e| acc.add(e)));
 
-    total.map(|n| Num(n)).map(|val| EvaluatedExpr(val))
+    total.map(|n| n.into_value()).map(|val| EvaluatedExpr(val))
 }
 
 fn sub(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
     get_nums(args)
-        .map(|v| v.into_iter().reduce(|acc, e| acc - e))?
+        .map(|v| v.into_iter().reduce(|acc, e| acc.sub(&e)))?
         .ok_or(Ex::new("Could not subtract provided expression"))
-        .map(|x| Num(x))
+        .map(|x| x.into_value())
         .map(|val| EvaluatedExpr(val))
 }
 
 fn mult(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
     get_nums(args)
-        .map(|v| v.into_iter().reduce(|acc, e| acc * e))?
+        .map(|v| v.into_iter().reduce(|acc, e| acc.mul(&e)))?
         .ok_or(Ex::new("Could not multiply provided expression"))
-        .map(|x| Num(x))
+        .map(|x| x.into_value())
         .map(|val| EvaluatedExpr(val))
 }
 
@@ -213,7 +214,7 @@ fn succ(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
 
     eval_args
         .get(0)
-        .map(|x| Num(x + 1))
+        .map(|x| x.add(&Number::Int(1)).into_value())
         .map(|val| EvaluatedExpr(val))
         .ok_or(Ex::new("Couldn't add num."))
 }
@@ -224,24 +225,616 @@ fn pred(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
 
     eval_args
         .get(0)
-        .map(|x| Num(x - 1))
+        .map(|x| x.sub(&Number::Int(1)).into_value())
         .map(|val| EvaluatedExpr(val))
         .ok_or(Ex::new("Couldn't subtract num.")) // err unreachable
 }
 
+// numbers, or strings by character. None when a NaN is involved
+fn compare(name: &str, args: &[Arg]) -> Result<Option<Ordering>> {
+    let eval_args = ev!(args);
+    check!(name, 2, eval_args);
+
+    match (&eval_args[0], &eval_args[1]) {
+        (Num(_) | BigNum(_) | Ratio(_) | Float(_), Num(_) | BigNum(_) | Ratio(_) | Float(_)) => {
+            let left = Number::from_value(&eval_args[0])?;
+            let right = Number::from_value(&eval_args[1])?;
+            Ok(left.compare(&right))
+        }
+        (Str(l), Str(r)) => Ok(Some(l.cmp(r))),
+        (Num(_) | BigNum(_) | Ratio(_) | Float(_), other) | (Str(_), other) | (other, _) => {
+            let msg = format!("'{}' can't compare '{}'", name, other.to_string());
+            Err(other.type_error("number", &msg))
+        }
+    }
+}
+
+fn lt(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let ord = compare(LT, args)?;
+    Ok(EvaluatedExpr(Bool(ord.is_some_and(Ordering::is_lt))))
+}
+
+fn gt(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let ord = compare(GT, args)?;
+    Ok(EvaluatedExpr(Bool(ord.is_some_and(Ordering::is_gt))))
+}
+
+fn le(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let ord = compare(LE, args)?;
+    Ok(EvaluatedExpr(Bool(ord.is_some_and(Ordering::is_le))))
+}
+
+fn ge(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let ord = compare(GE, args)?;
+    Ok(EvaluatedExpr(Bool(ord.is_some_and(Ordering::is_ge))))
+}
+
+fn not_equals(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(NOT_EQUALS, 2, eval_args);
+
+    Ok(EvaluatedExpr(Bool(!eval_args[0].equals(&eval_args[1]))))
+}
+
+fn divide(name: &str, args: &[Arg], op: fn(&Number, &Number) -> Option<Number>) -> Result<Number> {
+    let nums = get_nums(args)?;
+    check!(name, 2, nums);
+
+    match op(&nums[0], &nums[1]) {
+        Some(result) => Ok(result),
+        None => {
+            let left = nums[0].clone().into_value().to_string();
+            let msg = format!("'{}' can't divide {} by zero.", name, left);
+            Err(Ex::new(&msg).with_kind(ErrorKind::ZeroDivision))
+        }
+    }
+}
+
+// exact for integers: (div 6 4) => 3/2, (div 6 3) => 2. floats divide as usual: (div 7 2.0) => 3.5
+fn div(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let quot = divide(DIV, args, Number::div)?;
+    Ok(EvaluatedExpr(quot.into_value()))
+}
+
+// same sign as the divisor, so (add (mul (div a b) b) (mod a b)) is a
+fn modulo(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let rem = divide(MOD, args, |left, right| {
+        left.div_mod_floor(right).map(|(_, rem)| rem)
+    })?;
+    Ok(EvaluatedExpr(rem.into_value()))
+}
+
+// float or ratio to integer, integers pass through unchanged
+fn round_with(
+    name: &str,
+    args: &[Arg],
+    float_fn: fn(f64) -> f64,
+    ratio_fn: fn(&Rational) -> BigInt,
+) -> Result<Expression> {
+    let nums = get_nums(args)?;
+    check!(name, 1, nums);
+
+    match nums[0].to_int(float_fn, ratio_fn) {
+        Some(int) => Ok(EvaluatedExpr(int.into_value())),
+        None => errf!(
+            "'{}' can't convert {} to an integer.",
+            name,
+            nums[0].to_f64()
+        ),
+    }
+}
+
+fn floor(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    round_with(FLOOR, args, f64::floor, Rational::floor)
+}
+
+fn ceil(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    round_with(CEIL, args, f64::ceil, Rational::ceil)
+}
+
+// halves round away from zero: (round 2.5) => 3
+fn round(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    round_with(ROUND, args, f64::round, Rational::round)
+}
+
+// drops the fraction: (to-int -2.7) => -2
+fn to_int(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    round_with(TO_INT, args, f64::trunc, Rational::trunc)
+}
+
+fn to_float(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let nums = get_nums(args)?;
+    check!(TO_FLOAT, 1, nums);
+
+    Ok(EvaluatedExpr(Float(nums[0].to_f64())))
+}
+
 fn puts(args: &[Arg], context: &EvalContext) -> Result<Expression> {
     let values = ev!(args);
     values.iter().for_each(|x| println!("{}", x.to_string()));
     unit!()
 }
 
+// like puts, without the newline
+fn print(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let values: Vec<String> = ev!(args).iter().map(|x| x.to_string()).collect();
+    print!("{}", values.join(SPACE));
+    std::io::stdout().flush().ok();
+    unit!()
+}
+
+// (and a b c): stops at the first false value and gives it back, else the last one
+// the last arg is left for the evaluator, so it is in tail position
+fn and(args: &[Arg], context: &EvalContext) -> Result<Expression> {
+    short_circuit(args, context, false)
+}
+
+// (or a b c): stops at the first true value
+fn or(args: &[Arg], context: &EvalContext) -> Result<Expression> {
+    short_circuit(args, context, true)
+}
+
+// the args wait in a ShortCircuit frame on the stacks, not on the Rust stack
+fn short_circuit(args: &[Arg], context: &EvalContext, stop_at: bool) -> Result<Expression> {
+    let args = Arg::expect_all_uneval(args)?;
+    Ok(next_short_circuit(context, Rc::new(args), 0, stop_at))
+}
+
+fn not(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(NOT, 1, eval_args);
+
+    Ok(EvaluatedExpr(Bool(!eval_args[0].is_truthy())))
+}
+
 // ((> (puts 100)) (puts 200))
 fn chain(args: &[Arg], context: &EvalContext) -> Result<Expression> {
     let args = Arg::expect_all_uneval(args)?;
-    for node in args {
-        evaluate_outer(context.clone(), node, false)?;
+    let seq = Sequence {
+        ctx: context.clone(),
+        body: Rc::new(args),
+        tail: false,
+        bind: false,
+    };
+    Ok(SequenceExpr(seq))
+}
+
+// [a b c]: args are the evaluated elements
+fn list_literal(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let values = ev!(args);
+    Ok(EvaluatedExpr(ListValue(Rc::new(values))))
+}
+
+fn get_map(name: &str, value: &DataValue) -> Result<Rc<MapData>> {
+    match value {
+        MapValue(map) => Ok(Rc::clone(map)),
+        _ => {
+            let msg = format!("'{}' expected a map but got '{}'", name, value.to_string());
+            Err(value.type_error("map", &msg))
+        }
     }
-    unit!()
+}
+
+// for get and set, which take either
+fn list_or_map_error(name: &str, value: &DataValue) -> Ex {
+    let msg = format!(
+        "'{}' expected a list or map but got '{}'",
+        name,
+        value.to_string()
+    );
+    value.type_error("list or map", &msg)
+}
+
+// keys are looked up with equals, so they have to be values it can compare
+fn check_key(name: &str, key: &DataValue) -> Result<()> {
+    match key {
+        Num(_) | BigNum(_) | Ratio(_) | Float(_) | Bool(_) | Str(_) | ListValue(_)
+        | MapValue(_) => Ok(()),
+        _ => {
+            let msg = format!("'{}' can't use '{}' as a map key", name, key.to_string());
+            Err(key.type_error("number, boolean, string, list or map", &msg))
+        }
+    }
+}
+
+fn key_error(key: &DataValue) -> Ex {
+    let msg = format!("Key '{}' is not in the map.", key.to_string());
+    Ex::new(&msg).with_kind(ErrorKind::Key {
+        key: key.to_string(),
+    })
+}
+
+// {k1 v1 k2 v2}: args alternate keys and values, a repeated key keeps its last value
+fn map_literal(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let values = ev!(args);
+    let mut map = MapData::new();
+
+    for pair in values.chunks(2) {
+        check_key(MAP, &pair[0])?;
+        map.insert(pair[0].clone(), pair[1].clone());
+    }
+
+    Ok(EvaluatedExpr(MapValue(Rc::new(map))))
+}
+
+// (cons 1 [2 3]) => [1,2,3]
+// (cons [1 2] [3]) => [[1,2],3]
+fn cons(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(CONS, 2, eval_args);
+
+    let head = eval_args.get(0).unwrap();
+    let tail = eval_args.get(1).unwrap().expect_list()?;
+
+    let mut new_list = vec![head.clone()];
+    new_list.extend(tail.iter().cloned());
+
+    Ok(EvaluatedExpr(ListValue(Rc::new(new_list))))
+}
+
+// name is the builtin that was called, for errors. lists and lazy lists both work,
+// a lazy one can come from cons in a lazy context
+fn list_head(name: &str, value: &DataValue) -> Result<Expression> {
+    if let LazyList(lazy) = value {
+        return Ok(EvaluatedExpr(lazy.head.clone()));
+    }
+
+    let lst = value.expect_list()?;
+    match lst.first() {
+        Some(value) => Ok(EvaluatedExpr(value.clone())),
+        None => errf!("'{}' received an empty list.", name),
+    }
+}
+
+fn list_tail(name: &str, value: &DataValue) -> Result<Expression> {
+    if let LazyList(lazy) = value {
+        return lazy.force_tail().map(EvaluatedExpr);
+    }
+
+    let lst = value.expect_list()?;
+    if lst.is_empty() {
+        return errf!("'{}' received an empty list.", name);
+    }
+
+    let rest = lst.iter().skip(1).cloned().collect();
+    Ok(EvaluatedExpr(ListValue(Rc::new(rest))))
+}
+
+fn car(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(CAR, 1, eval_args);
+    list_head(CAR, eval_args.get(0).unwrap())
+}
+
+fn cdr(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(CDR, 1, eval_args);
+    list_tail(CDR, eval_args.get(0).unwrap())
+}
+
+fn get_index(lst: &[DataValue], value: &DataValue) -> Result<usize> {
+    let i = value.expect_num()?;
+
+    if i < 0 || i as usize >= lst.len() {
+        let msg = format!(
+            "Index {} is out of range for list of length {}.",
+            i,
+            lst.len()
+        );
+        return err!(msg);
+    }
+
+    Ok(i as usize)
+}
+
+// (idx [1 2 3] 0) => 1
+fn index(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(INDEX, 2, eval_args);
+
+    let lst = eval_args.get(0).unwrap().expect_list()?;
+    let i = get_index(lst, eval_args.get(1).unwrap())?;
+
+    Ok(EvaluatedExpr(lst[i].clone()))
+}
+
+// (get [1 2 3] 0) => 1, (get {"a" 1} "a") => 1
+fn get(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(GET, 2, eval_args);
+
+    match &eval_args[0] {
+        ListValue(lst) => {
+            let i = get_index(lst, eval_args.get(1).unwrap())?;
+            Ok(EvaluatedExpr(lst[i].clone()))
+        }
+        MapValue(map) => match map.get(&eval_args[1]) {
+            Some(value) => Ok(EvaluatedExpr(value.clone())),
+            None => Err(key_error(&eval_args[1])),
+        },
+        other => Err(list_or_map_error(GET, other)),
+    }
+}
+
+// (set [1 2 3] 0 5) => [5,2,3], (set {"a" 1} "b" 2) => {a 1,b 2}
+// a new list or map, the old one is unchanged
+fn set(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(SET, 3, eval_args);
+    let value = eval_args.get(2).unwrap().clone();
+
+    match &eval_args[0] {
+        ListValue(lst) => {
+            let i = get_index(lst, eval_args.get(1).unwrap())?;
+            let mut new_list = lst.as_ref().clone();
+            new_list[i] = value;
+            Ok(EvaluatedExpr(ListValue(Rc::new(new_list))))
+        }
+        MapValue(map) => {
+            check_key(SET, &eval_args[1])?;
+            let mut new_map = map.as_ref().clone();
+            new_map.insert(eval_args[1].clone(), value);
+            Ok(EvaluatedExpr(MapValue(Rc::new(new_map))))
+        }
+        other => Err(list_or_map_error(SET, other)),
+    }
+}
+
+// (del {"a" 1 "b" 2} "a") => {b 2}, a missing key leaves the map as it is
+fn del(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(DEL, 2, eval_args);
+
+    let map = get_map(DEL, &eval_args[0])?;
+    let new_map = match map.contains_key(&eval_args[1]) {
+        true => {
+            let mut new_map = map.as_ref().clone();
+            new_map.remove(&eval_args[1]);
+            Rc::new(new_map)
+        }
+        false => map,
+    };
+
+    Ok(EvaluatedExpr(MapValue(new_map)))
+}
+
+fn keys(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(KEYS, 1, eval_args);
+
+    let map = get_map(KEYS, &eval_args[0])?;
+    let keys = map.iter().map(|(key, _)| key.clone()).collect();
+    Ok(EvaluatedExpr(ListValue(Rc::new(keys))))
+}
+
+fn values(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(VALUES, 1, eval_args);
+
+    let map = get_map(VALUES, &eval_args[0])?;
+    let values = map.iter().map(|(_, value)| value.clone()).collect();
+    Ok(EvaluatedExpr(ListValue(Rc::new(values))))
+}
+
+fn has(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(HAS, 2, eval_args);
+
+    let map = get_map(HAS, &eval_args[0])?;
+    Ok(EvaluatedExpr(Bool(map.contains_key(&eval_args[1]))))
+}
+
+// (lcons head tail): evaluate head now, keep tail as a deferred expression
+fn lcons(args: &[Arg], context: &EvalContext) -> Result<Expression> {
+    lazy_cell(LCONS, args, context)
+}
+
+// cons in a lazy context: the same as lcons, so lists built with it can be infinite
+fn lazy_cons(args: &[Arg], context: &EvalContext) -> Result<Expression> {
+    lazy_cell(CONS, args, context)
+}
+
+fn lazy_cell(name: &str, args: &[Arg], context: &EvalContext) -> Result<Expression> {
+    let args = Arg::expect_all_uneval(args)?;
+    check!(name, 2, args);
+
+    let head = DeferredExpression {
+        ctx: context.clone(),
+        body: Rc::clone(args.get(0).unwrap()),
+    };
+    let tail = DeferredExpression {
+        ctx: context.clone(),
+        body: Rc::clone(args.get(1).unwrap()),
+    };
+
+    // the head is evaluated on the stacks, a LazyHead frame then makes the cell
+    Ok(WaitExpr(head, Continuation::LazyHead(tail)))
+}
+
+// lcar/lcdr also accept normal lists so streams can end in []
+fn lcar(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(LCAR, 1, eval_args);
+    list_head(LCAR, eval_args.get(0).unwrap())
+}
+
+fn lcdr(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(LCDR, 1, eval_args);
+    list_tail(LCDR, eval_args.get(0).unwrap())
+}
+
+// (concat "a" "b" "c") => "abc"
+fn concat(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    let strings: Result<Vec<&String>> = eval_args.iter().map(|x| x.expect_string()).collect();
+
+    Ok(EvaluatedExpr(Str(strings?
+        .into_iter()
+        .map(String::as_str)
+        .collect())))
+}
+
+// number of characters in a string or elements in a list
+fn length(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(LENGTH, 1, eval_args);
+
+    let len = match eval_args.get(0).unwrap() {
+        Str(string) => string.chars().count(),
+        ListValue(lst) => lst.len(),
+        MapValue(map) => map.len(),
+        other => {
+            let msg = format!(
+                "'{}' expected a string, list or map but got '{}'",
+                LENGTH,
+                other.to_string()
+            );
+            return Err(other.type_error("string, list or map", &msg));
+        }
+    };
+
+    Ok(EvaluatedExpr(Num(len as NumType)))
+}
+
+// (substring "hello" 1 3) => "el": characters from start up to end (exclusive)
+fn substring(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(SUBSTRING, 3, eval_args);
+
+    let string = eval_args.get(0).unwrap().expect_string()?;
+    let start = eval_args.get(1).unwrap().expect_num()?;
+    let end = eval_args.get(2).unwrap().expect_num()?;
+    let len = string.chars().count() as NumType;
+
+    if start < 0 || end < start || end > len {
+        let msg = format!(
+            "Substring range {}..{} is out of range for string of length {}.",
+            start, end, len
+        );
+        return err!(msg);
+    }
+
+    let sub: String = string
+        .chars()
+        .skip(start as usize)
+        .take((end - start) as usize)
+        .collect();
+
+    Ok(EvaluatedExpr(Str(sub)))
+}
+
+// (split "a,b" ",") => ["a","b"]
+fn split(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(SPLIT, 2, eval_args);
+
+    let string = eval_args.get(0).unwrap().expect_string()?;
+    let sep = eval_args.get(1).unwrap().expect_string()?;
+
+    if sep.is_empty() {
+        return errf!("'{}' received an empty separator.", SPLIT);
+    }
+
+    let parts = string
+        .split(sep.as_str())
+        .map(|x| Str(x.to_string()))
+        .collect();
+    Ok(EvaluatedExpr(ListValue(Rc::new(parts))))
+}
+
+// (join ["a" 1] "-") => "a-1"
+fn join(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(JOIN, 2, eval_args);
+
+    let lst = eval_args.get(0).unwrap().expect_list()?;
+    let sep = eval_args.get(1).unwrap().expect_string()?;
+
+    let strings: Vec<String> = lst.iter().map(|x| x.to_string()).collect();
+    Ok(EvaluatedExpr(Str(strings.join(sep.as_str()))))
+}
+
+fn to_number(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(TO_NUMBER, 1, eval_args);
+
+    let string = eval_args.get(0).unwrap().expect_string()?;
+    if let Ok(num) = string.trim().parse::<NumType>() {
+        return Ok(EvaluatedExpr(Num(num)));
+    }
+
+    let trimmed = string.trim();
+    if let Some(big) = BigInt::parse(trimmed) {
+        return Ok(EvaluatedExpr(BigNum(Rc::new(big))));
+    }
+
+    if let Some(ratio) = Rational::parse(trimmed) {
+        return Ok(EvaluatedExpr(Number::Ratio(Rc::new(ratio)).into_value()));
+    }
+
+    match parse_float(trimmed) {
+        Some(float) => Ok(EvaluatedExpr(Float(float))),
+        None => errf!("Can't convert '{}' to a number.", string),
+    }
+}
+
+fn stringify(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(TO_STRING, 1, eval_args);
+
+    let value = eval_args.get(0).unwrap();
+    Ok(EvaluatedExpr(Str(value.to_string())))
+}
+
+// (error "msg") raises a user error, (error e) raises a caught error again
+fn error(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(ERROR, 1, eval_args);
+
+    match eval_args.get(0).unwrap() {
+        ErrorValue(err) => Err(err.to_ex()),
+        value => Err(Ex::new(&value.to_string()).with_kind(ErrorKind::User)),
+    }
+}
+
+fn get_error(name: &str, value: &DataValue) -> Result<Rc<ErrorData>> {
+    match value {
+        ErrorValue(err) => Ok(Rc::clone(err)),
+        _ => {
+            let msg = format!(
+                "'{}' expected an error but got '{}'",
+                name,
+                value.to_string()
+            );
+            Err(value.type_error("error", &msg))
+        }
+    }
+}
+
+fn err_msg(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(ERR_MSG, 1, eval_args);
+
+    let err = get_error(ERR_MSG, eval_args.get(0).unwrap())?;
+    Ok(EvaluatedExpr(Str(err.message.clone())))
+}
+
+// kind name e.g "TypeError"
+fn err_kind(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(ERR_KIND, 1, eval_args);
+
+    let err = get_error(ERR_KIND, eval_args.get(0).unwrap())?;
+    Ok(EvaluatedExpr(Str(err.kind.name().to_string())))
+}
+
+// result of a try body: passed through, the frame marks where to unwind to
+fn try_result(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let eval_args = ev!(args);
+    check!(TRY_NAME, 1, eval_args);
+
+    Ok(EvaluatedExpr(eval_args.into_iter().next().unwrap()))
 }
 
 // Builders
@@ -293,6 +886,102 @@ pub fn build_pred() -> BuiltIn {
         .build()
 }
 
+pub fn build_lt() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LT)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(lt)
+        .build()
+}
+
+pub fn build_gt() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(GT)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(gt)
+        .build()
+}
+
+pub fn build_le() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LE)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(le)
+        .build()
+}
+
+pub fn build_ge() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(GE)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(ge)
+        .build()
+}
+
+pub fn build_not_equals() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(NOT_EQUALS)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(not_equals)
+        .build()
+}
+
+pub fn build_div() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(DIV)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(div)
+        .build()
+}
+
+pub fn build_mod() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(MOD)
+        .params(Params::new_finite(vec!["left", "right"]))
+        .exec(modulo)
+        .build()
+}
+
+pub fn build_floor() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(FLOOR)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(floor)
+        .build()
+}
+
+pub fn build_ceil() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(CEIL)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(ceil)
+        .build()
+}
+
+pub fn build_round() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(ROUND)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(round)
+        .build()
+}
+
+pub fn build_to_int() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(TO_INT)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(to_int)
+        .build()
+}
+
+pub fn build_to_float() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(TO_FLOAT)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(to_float)
+        .build()
+}
+
 pub fn build_puts() -> BuiltIn {
     BuiltInBuilder::new_default()
         .name(PUTS)
@@ -301,6 +990,40 @@ pub fn build_puts() -> BuiltIn {
         .build()
 }
 
+pub fn build_print() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(PRINT)
+        .params(Params::new_infinite(1))
+        .exec(print)
+        .build()
+}
+
+pub fn build_and() -> BuiltIn {
+    BuiltInBuilder::new()
+        .name(AND)
+        .params(Params::new_infinite(2))
+        .arg_type(ArgType::Unevaluated)
+        .exec(and)
+        .build()
+}
+
+pub fn build_or() -> BuiltIn {
+    BuiltInBuilder::new()
+        .name(OR)
+        .params(Params::new_infinite(2))
+        .arg_type(ArgType::Unevaluated)
+        .exec(or)
+        .build()
+}
+
+pub fn build_not() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(NOT)
+        .params(Params::new_finite(vec!["value"]))
+        .exec(not)
+        .build()
+}
+
 pub fn build_chain() -> BuiltIn {
     BuiltInBuilder::new()
         .name(CHAIN)
@@ -309,3 +1032,224 @@ pub fn build_chain() -> BuiltIn {
         .exec(chain)
         .build()
 }
+
+// not registered: used by the evaluator for list literals
+pub fn build_list_literal() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LIST)
+        .params(Params::new_infinite(0))
+        .exec(list_literal)
+        .build()
+}
+
+// not registered: used by the evaluator for map literals
+pub fn build_map_literal() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(MAP)
+        .params(Params::new_infinite(0))
+        .exec(map_literal)
+        .build()
+}
+
+pub fn build_del() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(DEL)
+        .params(Params::new_finite(vec!["map", "key"]))
+        .exec(del)
+        .build()
+}
+
+pub fn build_keys() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(KEYS)
+        .params(Params::new_finite(vec!["map"]))
+        .exec(keys)
+        .build()
+}
+
+pub fn build_values() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(VALUES)
+        .params(Params::new_finite(vec!["map"]))
+        .exec(values)
+        .build()
+}
+
+pub fn build_has() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(HAS)
+        .params(Params::new_finite(vec!["map", "key"]))
+        .exec(has)
+        .build()
+}
+
+pub fn build_cons() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(CONS)
+        .params(Params::new_finite(vec!["head", "tail"]))
+        .exec(cons)
+        .build()
+}
+
+pub fn build_lazy_cons() -> BuiltIn {
+    BuiltInBuilder::new()
+        .name(CONS)
+        .params(Params::new_finite(vec!["head", "tail"]))
+        .arg_type(ArgType::Unevaluated)
+        .exec(lazy_cons)
+        .build()
+}
+
+pub fn build_car() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(CAR)
+        .params(Params::new_finite(vec!["lst"]))
+        .exec(car)
+        .build()
+}
+
+pub fn build_cdr() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(CDR)
+        .params(Params::new_finite(vec!["lst"]))
+        .exec(cdr)
+        .build()
+}
+
+pub fn build_index() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(INDEX)
+        .params(Params::new_finite(vec!["lst", "i"]))
+        .exec(index)
+        .build()
+}
+
+pub fn build_get() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(GET)
+        .params(Params::new_finite(vec!["coll", "key"]))
+        .exec(get)
+        .build()
+}
+
+pub fn build_set() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(SET)
+        .params(Params::new_finite(vec!["coll", "key", "value"]))
+        .exec(set)
+        .build()
+}
+
+pub fn build_lcons() -> BuiltIn {
+    BuiltInBuilder::new()
+        .name(LCONS)
+        .params(Params::new_finite(vec!["head", "tail"]))
+        .arg_type(ArgType::Unevaluated)
+        .exec(lcons)
+        .build()
+}
+
+pub fn build_lcar() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LCAR)
+        .params(Params::new_finite(vec!["lst"]))
+        .exec(lcar)
+        .build()
+}
+
+pub fn build_lcdr() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LCDR)
+        .params(Params::new_finite(vec!["lst"]))
+        .exec(lcdr)
+        .build()
+}
+
+pub fn build_concat() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(CONCAT)
+        .params(Params::new_infinite(2))
+        .exec(concat)
+        .build()
+}
+
+pub fn build_length() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(LENGTH)
+        .params(Params::new_finite(vec!["value"]))
+        .exec(length)
+        .build()
+}
+
+pub fn build_substring() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(SUBSTRING)
+        .params(Params::new_finite(vec!["string", "start", "end"]))
+        .exec(substring)
+        .build()
+}
+
+pub fn build_split() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(SPLIT)
+        .params(Params::new_finite(vec!["string", "sep"]))
+        .exec(split)
+        .build()
+}
+
+pub fn build_join() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(JOIN)
+        .params(Params::new_finite(vec!["lst", "sep"]))
+        .exec(join)
+        .build()
+}
+
+pub fn build_to_number() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(TO_NUMBER)
+        .params(Params::new_finite(vec!["string"]))
+        .exec(to_number)
+        .build()
+}
+
+pub fn build_to_string() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(TO_STRING)
+        .params(Params::new_finite(vec!["value"]))
+        .exec(stringify)
+        .build()
+}
+
+pub fn build_error() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(ERROR)
+        .params(Params::new_finite(vec!["value"]))
+        .exec(error)
+        .build()
+}
+
+pub fn build_err_msg() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(ERR_MSG)
+        .params(Params::new_finite(vec!["err"]))
+        .exec(err_msg)
+        .build()
+}
+
+pub fn build_err_kind() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(ERR_KIND)
+        .params(Params::new_finite(vec!["err"]))
+        .exec(err_kind)
+        .build()
+}
+
+// not registered: pushed on fn_stack for each try
+pub fn build_try() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(TRY_NAME)
+        .params(Params::new_finite(vec!["body"]))
+        .exec(try_result)
+        .build()
+}
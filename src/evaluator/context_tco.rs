@@ -1,25 +1,41 @@
 use std::borrow::Borrow;
 use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 use crate::constants::*;
 
 use super::builtins_tco::*;
 use super::data_tco::*;
 use super::function_tco::*;
+use super::limits::EvalLimits;
+use super::math::*;
+
+// how user functions get their args, fixed where the function is defined
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Strategy {
+    #[default]
+    Strict, // evaluated before the call
+    Lazy, // thunks, evaluated the first time they're read
+}
 
 // wrapper around Rc<RefCell<Context>>
 #[derive(Clone)]
 pub struct EvalContext {
     ctx: Rc<RefCell<Context>>,
+    globals: Option<Weak<RefCell<Context>>>, // the top level ctx, None if this is it
+    limits: EvalLimits, // only read from the context a top level evaluation starts with
+    strategy: Strategy,
 }
 
 impl EvalContext {
     pub fn new() -> EvalContext {
         EvalContext {
             ctx: Rc::new(RefCell::new(setup_context())),
+            globals: None,
+            limits: EvalLimits::default(),
+            strategy: Strategy::Strict,
         }
     }
 
@@ -27,43 +43,103 @@ impl EvalContext {
         let new_ctx = ctx.clone();
         EvalContext {
             ctx: Rc::new(RefCell::new(new_ctx)),
+            globals: None,
+            limits: EvalLimits::default(),
+            strategy: Strategy::Strict,
         }
     }
 
-    // eval version
-    // makes a new context with key value pairs inserted from other_ctx only if they don't exist
-    pub fn merge_context(&self, other_ctx: &EvalContext) -> EvalContext {
-        let mut new_ctx = self.read().clone();
+    pub fn with_limits(mut self, limits: EvalLimits) -> EvalContext {
+        self.limits = limits;
+        self
+    }
 
-        for (key, value) in &other_ctx.read().symbol_map {
-            if new_ctx.get_data_value(&key).is_none() {
-                new_ctx.symbol_map.insert(key.clone(), value.clone());
-            }
-        }
-        EvalContext::new_from_context(&new_ctx)
+    pub fn get_limits(&self) -> EvalLimits {
+        self.limits
+    }
+
+    // kept by copies, so functions defined in self and the ctx they run in use it too
+    // lazy also makes cons defer its tail, so lists can be infinite
+    pub fn with_strategy(mut self, strategy: Strategy) -> EvalContext {
+        self.strategy = strategy;
+        let cons = match strategy {
+            Strategy::Strict => build_cons(),
+            Strategy::Lazy => build_lazy_cons(),
+        };
+        self.write().add_function(CONS, Rc::new(cons));
+        self
+    }
+
+    pub fn get_strategy(&self) -> Strategy {
+        self.strategy
     }
 
-    // writes key value pairs from other_ctx into self, consuming other_ctx
+    // writes the bindings visible in other_ctx into self, consuming other_ctx
     // useful for returning out from evaluate: we don't need the returned ctx after copying in
     pub fn write_context(&mut self, other_ctx: EvalContext) {
-        for (key, value) in other_ctx.read().to_owned().symbol_map.into_iter() {
+        for (key, value) in other_ctx.read().bindings().into_iter() {
             self.write().add_variable(key.as_str(), value);
         }
     }
 
-    pub fn read(&self) -> Ref<Context> {
+    // frame for copies to share: later writes to self go in a new frame on top
+    fn freeze(&self) -> Option<Rc<Context>> {
+        self.ctx.as_ref().borrow_mut().freeze()
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.globals.is_none()
+    }
+
+    fn globals_ref(&self) -> Option<Weak<RefCell<Context>>> {
+        match &self.globals {
+            Some(globals) => Some(Weak::clone(globals)),
+            None => Some(Rc::downgrade(&self.ctx)),
+        }
+    }
+
+    // looks in the frames of self, then in globals
+    pub fn get(&self, name: &str) -> Option<DataValue> {
+        if let Some(value) = self.read().get_data_value(name) {
+            return Some(value.clone());
+        }
+
+        let globals = self.globals.as_ref()?.upgrade()?;
+        let globals = globals.as_ref().borrow();
+        globals.get_data_value(name).cloned()
+    }
+
+    pub fn read(&self) -> Ref<'_, Context> {
         self.ctx.as_ref().borrow()
     }
 
-    pub fn write(&mut self) -> RefMut<Context> {
+    pub fn write(&mut self) -> RefMut<'_, Context> {
         self.ctx.as_ref().borrow_mut()
     }
 
     // clone gives the same Rc ptr, we need a method for making a new copy
+    // the copy is an empty frame over the frozen bindings of self, so it is O(1)
     pub fn copy(&self) -> EvalContext {
-        let existing = self.read().clone();
         EvalContext {
-            ctx: Rc::new(RefCell::new(existing)),
+            ctx: Rc::new(RefCell::new(Context::child(self.freeze()))),
+            globals: self.globals_ref(),
+            limits: self.limits,
+            strategy: self.strategy,
+        }
+    }
+
+    // what a function defined in self closes over
+    // at the top level that is just globals, so later definitions are visible
+    pub fn capture(&self) -> EvalContext {
+        if self.globals.is_some() {
+            return self.copy();
+        }
+
+        EvalContext {
+            ctx: Rc::new(RefCell::new(Context::new())),
+            globals: self.globals_ref(),
+            limits: self.limits,
+            strategy: self.strategy,
         }
     }
 
@@ -71,19 +147,16 @@ impl EvalContext {
         let mut vars: Vec<String> = vec![];
         let mut fns: Vec<String> = vec![];
 
-        let ctx = self.read();
-
-        for key in ctx.symbol_map.keys() {
-            let var = ctx.get_function(key);
+        let bindings = self.read().bindings();
 
-            if var.is_some() {
-                let repr = format!("Function: {} => {}", key, var.unwrap().to_string());
+        for (key, value) in bindings.iter() {
+            if let Ok(var) = value.expect_function() {
+                let repr = format!("Function: {} => {}", key, var.to_string());
                 fns.push(repr);
                 continue;
             }
 
-            let var = ctx.get_variable(key).unwrap();
-            let repr = format!("Variable: {} => {}", key, var.to_string());
+            let repr = format!("Variable: {} => {}", key, value.to_string());
             vars.push(repr);
         }
 
@@ -99,9 +172,14 @@ impl EvalContext {
     }
 }
 
+// a frame of bindings: names are looked up in symbol_map, then parent
+// frames below the top one are frozen and shared between contexts, so copies are cheap
+// use bindings() to see everything: symbol_map only has this frame
 #[derive(Clone)]
 pub struct Context {
-    pub symbol_map: HashMap<String, DataValue>,
+    symbol_map: HashMap<String, DataValue>, // bindings made in this frame
+    deleted: HashSet<String>,               // hidden in this frame even if parent has them
+    parent: Option<Rc<Context>>,
 }
 
 pub fn setup_context() -> Context {
@@ -123,10 +201,73 @@ pub fn setup_context() -> Context {
     regb!(build_sub);
     regb!(build_mult);
     regb!(build_equals);
+    regb!(build_not_equals);
+    regb!(build_lt);
+    regb!(build_gt);
+    regb!(build_le);
+    regb!(build_ge);
+    regb!(build_div);
+    regb!(build_mod);
+    regb!(build_floor);
+    regb!(build_ceil);
+    regb!(build_round);
+    regb!(build_to_int);
+    regb!(build_to_float);
+    regb!(build_abs);
+    regb!(build_min);
+    regb!(build_max);
+    regb!(build_pow);
+    regb!(build_sqrt);
+    regb!(build_gcd);
+    regb!(build_lcm);
+    regb!(build_sign);
+    regb!(build_even);
+    regb!(build_odd);
+    regb!(build_sin);
+    regb!(build_cos);
+    regb!(build_tan);
+    regb!(build_asin);
+    regb!(build_acos);
+    regb!(build_atan);
+    regb!(build_exp);
+    regb!(build_log);
+    regb!(build_log10);
+    regb!(build_band);
+    regb!(build_bor);
+    regb!(build_bxor);
+    regb!(build_shl);
+    regb!(build_shr);
+    regb!(build_and);
+    regb!(build_or);
+    regb!(build_not);
     regb!(build_succ);
     regb!(build_pred);
     regb!(build_puts);
+    regb!(build_print);
     regb!(build_chain);
+    regb!(build_cons);
+    regb!(build_car);
+    regb!(build_cdr);
+    regb!(build_index);
+    regb!(build_get);
+    regb!(build_set);
+    regb!(build_del);
+    regb!(build_keys);
+    regb!(build_values);
+    regb!(build_has);
+    regb!(build_lcons);
+    regb!(build_lcar);
+    regb!(build_lcdr);
+    regb!(build_concat);
+    regb!(build_length);
+    regb!(build_substring);
+    regb!(build_split);
+    regb!(build_join);
+    regb!(build_to_number);
+    regb!(build_to_string);
+    regb!(build_error);
+    regb!(build_err_msg);
+    regb!(build_err_kind);
 
     // reg!(ADD, Add);
     // reg!(SUB, Sub);
@@ -145,32 +286,90 @@ pub fn setup_context() -> Context {
 // take in a dyn Function and add to map
 impl Context {
     pub fn new() -> Context {
-        let symbol_map: HashMap<String, DataValue> = HashMap::new();
-        Context { symbol_map }
+        Context::child(None)
+    }
+
+    fn child(parent: Option<Rc<Context>>) -> Context {
+        Context {
+            symbol_map: HashMap::new(),
+            deleted: HashSet::new(),
+            parent,
+        }
+    }
+
+    // move this frame's bindings into a shared frame below it, which is returned
+    fn freeze(&mut self) -> Option<Rc<Context>> {
+        if self.symbol_map.is_empty() && self.deleted.is_empty() {
+            return self.parent.clone();
+        }
+
+        let frozen = Rc::new(Context {
+            symbol_map: std::mem::take(&mut self.symbol_map),
+            deleted: std::mem::take(&mut self.deleted),
+            parent: self.parent.take(),
+        });
+
+        self.parent = Some(Rc::clone(&frozen));
+        Some(frozen)
     }
 
     pub fn add_variable(&mut self, ident: &str, value: DataValue) {
+        self.deleted.remove(ident);
         self.symbol_map.insert(ident.to_string(), value);
     }
 
     pub fn delete_variable(&mut self, name: &str) {
         self.symbol_map.remove(name);
+        if self.parent.is_some() {
+            self.deleted.insert(name.to_string());
+        }
     }
 
     pub fn add_function(&mut self, name: &str, function: Rc<dyn Function>) {
         let d = DataValue::FunctionVariable(function);
-        self.symbol_map.insert(name.to_string(), d);
+        self.add_variable(name, d);
     }
 
     // for getting something either a variable or a function
-    pub fn get_data_value(&self, name: &String) -> Option<&DataValue> {
-        self.symbol_map.get(name)
+    // frames are searched from this one down
+    pub fn get_data_value(&self, name: &str) -> Option<&DataValue> {
+        let mut frame = Some(self);
+
+        while let Some(ctx) = frame {
+            if let Some(value) = ctx.symbol_map.get(name) {
+                return Some(value);
+            }
+
+            if ctx.deleted.contains(name) {
+                return None;
+            }
+            frame = ctx.parent.as_deref();
+        }
+        None
+    }
+
+    // everything visible from this frame, flattened
+    pub fn bindings(&self) -> HashMap<String, DataValue> {
+        let mut names: HashSet<&String> = HashSet::new();
+        let mut frame = Some(self);
+
+        while let Some(ctx) = frame {
+            names.extend(ctx.symbol_map.keys());
+            frame = ctx.parent.as_deref();
+        }
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.get_data_value(name)?;
+                Some((name.clone(), value.clone()))
+            })
+            .collect()
     }
 
     // reference is enough: we never have to mutate
     pub fn get_function(&self, name: &str) -> Option<&Rc<dyn Function>> {
-        self.symbol_map
-            .get(name)
+        self.get_data_value(name)
             .and_then(|data| data.expect_function().ok())
     }
 
@@ -179,13 +378,13 @@ impl Context {
         if self.get_function(name).is_some() {
             None
         } else {
-            self.symbol_map.get(name)
+            self.get_data_value(name)
         }
     }
 
     pub fn to_string(&self) -> String {
         let mut pairs: Vec<String> = vec![];
-        for (key, value) in self.symbol_map.iter() {
+        for (key, value) in self.bindings().iter() {
             let m = format!("{}: {}", key.to_string(), value.to_string());
             pairs.push(m);
         }
@@ -193,6 +392,52 @@ impl Context {
     }
 }
 
+// long chains of frames (from deep recursion), or of thunks in lazy mode,
+// would overflow the stack if dropped recursively
+impl Drop for Context {
+    fn drop(&mut self) {
+        let mut frames: Vec<Rc<Context>> = vec![];
+        let mut thunk_ctxs: Vec<Rc<RefCell<Context>>> = vec![];
+        self.release(&mut frames, &mut thunk_ctxs);
+
+        loop {
+            if let Some(frame) = frames.pop() {
+                if let Ok(mut frame) = Rc::try_unwrap(frame) {
+                    frame.release(&mut frames, &mut thunk_ctxs);
+                }
+            } else if let Some(ctx) = thunk_ctxs.pop() {
+                if let Ok(ctx) = Rc::try_unwrap(ctx) {
+                    ctx.into_inner().release(&mut frames, &mut thunk_ctxs);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl Context {
+    // hand over what this frame is the last owner of, so dropping it doesn't recurse
+    fn release(
+        &mut self,
+        frames: &mut Vec<Rc<Context>>,
+        thunk_ctxs: &mut Vec<Rc<RefCell<Context>>>,
+    ) {
+        frames.extend(self.parent.take());
+
+        for (_, value) in self.symbol_map.drain() {
+            let thunk = match value {
+                ThunkValue(thunk) => thunk,
+                _ => continue,
+            };
+
+            if let Some(expr) = Rc::try_unwrap(thunk).ok().and_then(Thunk::into_expression) {
+                thunk_ctxs.push(expr.ctx.ctx);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -322,34 +567,66 @@ pub mod tests {
     }
 
     #[test]
-    pub fn context_test_merge() {
+    fn context_test_frames() {
         let mut c = EvalContext::new();
+        c.write().add_variable("x", Num(1));
 
-        c.write().add_variable("x", Num(2));
-        c.write().add_variable("y", Num(3));
-
+        // writes after a copy don't leak either way
         let mut c2 = c.copy();
+        c2.write().add_variable("y", Num(2));
+        c.write().add_variable("z", Num(3));
+
+        assert!(c.read().get_variable("y").is_none());
+        assert!(c2.read().get_data_value(&"z".to_string()).is_none());
+        assert!(c2.read().get_data_value(&"x".to_string()).is_some());
+
+        // delete hides the binding from the frame below
+        c2.write().delete_variable("x");
+        assert!(c2.read().get_data_value(&"x".to_string()).is_none());
+        assert!(c.read().get_data_value(&"x".to_string()).is_some());
+
+        // and adding it back makes it visible again
+        c2.write().add_variable("x", Num(4));
+        let x = c2.read().get_variable("x").unwrap().expect_num().unwrap();
+        assert_eq!(x, 4);
+
+        let bindings = c2.read().bindings();
+        assert!(bindings.contains_key("add"));
+        assert!(bindings.contains_key("y"));
+        assert!(!bindings.contains_key("z"));
+    }
+
+    #[test]
+    fn context_test_lookup_frames() {
+        // bindings stay visible once evaluation or copy has moved them into a parent frame
+        let mut c = EvalContext::new();
+        c.write().add_variable("x", Num(1));
+        crate::evaluate_all("let y 2", &mut c).expect("Should evaluate");
+
+        assert!(c.read().get_function("add").is_some());
+        assert!(c.read().get_function("x").is_none());
+        let x = c.read().get_variable("x").unwrap().expect_num().unwrap();
+        assert_eq!(x, 1);
+        let y = c.read().get_variable("y").unwrap().expect_num().unwrap();
+        assert_eq!(y, 2);
+
+        let c2 = c.copy();
+        assert!(c2.read().get_function("add").is_some());
+        let x = c2.read().get_variable("x").unwrap().expect_num().unwrap();
+        assert_eq!(x, 1);
+        assert!(c2.read().get_variable("add").is_none());
+    }
 
-        c2.write().add_variable("x", Num(5));
-        c2.write().add_variable("y", Num(10));
-        c2.write().add_variable("z", Num(10));
-
-        let c3 = c.merge_context(&c2);
-        assert_eq!(
-            c3.read()
-                .get_data_value(&"x".to_string())
-                .unwrap()
-                .expect_num()
-                .unwrap(),
-            2
-        );
-        assert_eq!(
-            c3.read()
-                .get_data_value(&"z".to_string())
-                .unwrap()
-                .expect_num()
-                .unwrap(),
-            10
-        );
+    #[test]
+    fn context_test_builtins() {
+        // every name listed as a builtin is registered
+        let c = EvalContext::new();
+        for name in BUILTINS {
+            assert!(
+                c.read().get_function(name).is_some(),
+                "'{}' isn't registered",
+                name
+            );
+        }
     }
 }
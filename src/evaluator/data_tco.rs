@@ -1,12 +1,21 @@
-use std::fmt::Display;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
 use std::rc::Rc;
 
-use crate::constants::NumType;
+use crate::constants::{NumType, CLOSE_LIST, CLOSE_MAP, OPEN_LIST, OPEN_MAP, VAR_SEP};
+use crate::lexer::escape_string;
 use crate::message::*;
 use crate::parser::parse_node::ASTNode;
 
+use super::adt::AdtData;
+use super::bigint::BigInt;
 use super::context_tco::EvalContext;
+use super::evaluator_tco::{evaluate_outer, DeferredExpression};
 use super::function_tco::{Function, UserFunction};
+use super::map::MapData;
+use super::number::{format_float, Number};
+use super::rational::Rational;
 
 // Number, Boolean, List, String, Lambda, FunctionVariable(Box<dyn Function>)
 
@@ -24,11 +33,161 @@ impl LetReturn {
         }
     }
 }
+// deferred until it is first needed, then memoized
+#[derive(Clone)]
+enum Delayed {
+    Deferred(DeferredExpression),
+    Forced(DataValue),
+}
+
+// (lcons head tail): head is evaluated, tail is not
+pub struct LazyCons {
+    pub head: DataValue,
+    tail: RefCell<Delayed>,
+}
+
+impl LazyCons {
+    pub fn new(head: DataValue, tail: DeferredExpression) -> LazyCons {
+        LazyCons {
+            head,
+            tail: RefCell::new(Delayed::Deferred(tail)),
+        }
+    }
+
+    // evaluate the tail on first access and cache it for later calls
+    pub fn force_tail(&self) -> Result<DataValue> {
+        let deferred = match &*self.tail.borrow() {
+            Delayed::Forced(value) => return Ok(value.clone()),
+            Delayed::Deferred(deferred) => deferred.clone(),
+        };
+
+        let value = evaluate_outer(deferred.ctx, deferred.body, false)?;
+        self.tail.replace(Delayed::Forced(value.clone()));
+        Ok(value)
+    }
+}
+
+// forced part of the list, then ... for the rest
+impl fmt::Display for LazyCons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut items = vec![self.head.to_nested_string()];
+        let mut tail = self.tail.borrow().clone();
+
+        loop {
+            match tail {
+                Delayed::Forced(LazyList(next)) => {
+                    items.push(next.head.to_nested_string());
+                    tail = next.tail.borrow().clone();
+                }
+                Delayed::Forced(ListValue(lst)) => {
+                    items.extend(lst.iter().map(|x| x.to_nested_string()));
+                    break;
+                }
+                Delayed::Forced(other) => {
+                    items.push(other.to_nested_string());
+                    break;
+                }
+                Delayed::Deferred(_) => {
+                    items.push(String::from("..."));
+                    break;
+                }
+            }
+        }
+
+        write!(f, "{}{}{}", OPEN_LIST, items.join(VAR_SEP), CLOSE_LIST)
+    }
+}
+
+// unlink forced tails one cell at a time, so dropping a long stream doesn't recurse
+impl Drop for LazyCons {
+    fn drop(&mut self) {
+        let mut tail = self.tail.replace(Delayed::Forced(Unit));
+
+        while let Delayed::Forced(LazyList(next)) = tail {
+            match Rc::try_unwrap(next) {
+                Ok(cell) => tail = cell.tail.replace(Delayed::Forced(Unit)),
+                Err(_) => break, // still shared: the other owner drops it
+            }
+        }
+    }
+}
+
+// an argument passed by need: evaluated the first time it's read
+// forcing happens on the stacks, see force_thunk
+pub struct Thunk {
+    state: RefCell<Delayed>,
+}
+
+impl Thunk {
+    pub fn new(expr: DeferredExpression) -> Thunk {
+        Thunk {
+            state: RefCell::new(Delayed::Deferred(expr)),
+        }
+    }
+
+    // None until forced
+    pub fn value(&self) -> Option<DataValue> {
+        match &*self.state.borrow() {
+            Delayed::Forced(value) => Some(value.clone()),
+            Delayed::Deferred(_) => None,
+        }
+    }
+
+    // None once forced
+    pub fn expression(&self) -> Option<DeferredExpression> {
+        match &*self.state.borrow() {
+            Delayed::Deferred(expr) => Some(expr.clone()),
+            Delayed::Forced(_) => None,
+        }
+    }
+
+    pub fn set(&self, value: DataValue) {
+        self.state.replace(Delayed::Forced(value));
+    }
+
+    pub(crate) fn into_expression(self) -> Option<DeferredExpression> {
+        match self.state.into_inner() {
+            Delayed::Deferred(expr) => Some(expr),
+            Delayed::Forced(_) => None,
+        }
+    }
+}
+
+// an error caught by try/catch, bound to the name in the catch
+pub struct ErrorData {
+    pub message: String,
+    pub kind: ErrorKind,
+}
+
+impl ErrorData {
+    pub fn from_ex(ex: &Ex) -> ErrorData {
+        ErrorData {
+            message: ex.get_message().to_string(),
+            kind: ex.get_kind().clone(),
+        }
+    }
+
+    // raise again with the same kind
+    pub fn to_ex(&self) -> Ex {
+        Ex::new(&self.message).with_kind(self.kind.clone())
+    }
+}
+
 // Function shouldn't get dropped until all refs in context/args are dropped -> use Rc
 #[derive(Clone, AsRefStr)]
 pub enum DataValue {
     Num(NumType),
+    BigNum(Rc<BigInt>),  // only for values that don't fit in Num
+    Ratio(Rc<Rational>), // only for fractions that aren't whole numbers
+    Float(f64),
     Bool(bool),
+    Str(String),
+    ListValue(Rc<Vec<DataValue>>), // shared: car/cdr/cons build new lists without mutating
+    MapValue(Rc<MapData>),         // shared like lists, in insertion order
+    LazyList(Rc<LazyCons>),        // built by lcons, possibly infinite
+    AdtValue(Rc<AdtData>),         // built by a constructor from a data declaration
+    ErrorValue(Rc<ErrorData>),     // bound by catch
+    ThunkValue(Rc<Thunk>), // lazy arg, only ever bound in a ctx: reading the symbol forces it
     FunctionVariable(Rc<dyn Function>), // we need to borrow the function from Context when doing this
     SetVar(LetReturn),                  // returned from 'let' if outer_call=true,
     SetFn(Rc<UserFunction>),
@@ -36,14 +195,17 @@ pub enum DataValue {
 }
 
 impl DataValue {
+    // by value across all the number types: (eq 1 1.0) is true
     pub fn num_equals(&self, other: &DataValue) -> bool {
-        if let Ok(num) = self.expect_num() {
-            match other {
-                Num(other_num) => num.eq(other_num),
+        match (self, other) {
+            (
+                Num(_) | BigNum(_) | Ratio(_) | Float(_),
+                Num(_) | BigNum(_) | Ratio(_) | Float(_),
+            ) => match (Number::from_value(self), Number::from_value(other)) {
+                (Ok(left), Ok(right)) => left.compare(&right) == Some(Ordering::Equal),
                 _ => false,
-            }
-        } else {
-            false
+            },
+            _ => false,
         }
     }
 
@@ -58,33 +220,139 @@ impl DataValue {
         }
     }
 
+    pub fn str_equals(&self, other: &DataValue) -> bool {
+        match (self, other) {
+            (Str(left), Str(right)) => left.eq(right),
+            _ => false,
+        }
+    }
+
+    // element-wise comparison
+    pub fn list_equals(&self, other: &DataValue) -> bool {
+        match (self, other) {
+            (ListValue(left), ListValue(right)) => {
+                left.len() == right.len() && left.iter().zip(right.iter()).all(|(l, r)| l.equals(r))
+            }
+            _ => false,
+        }
+    }
+
+    // same keys with equal values, in any order
+    pub fn map_equals(&self, other: &DataValue) -> bool {
+        match (self, other) {
+            (MapValue(left), MapValue(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, value)| right.get(key).is_some_and(|v| v.equals(value)))
+            }
+            _ => false,
+        }
+    }
+
     pub fn equals(&self, right: &DataValue) -> bool {
         match self {
-            Num(_n) => self.num_equals(right),
+            Num(_) | BigNum(_) | Ratio(_) | Float(_) => self.num_equals(right),
             Bool(_b) => self.bool_equals(right),
+            Str(_s) => self.str_equals(right),
+            ListValue(_lst) => self.list_equals(right),
+            MapValue(_map) => self.map_equals(right),
+            AdtValue(adt) => match right {
+                AdtValue(other) => adt.equals(other),
+                _ => false,
+            },
+            // can't compare infinite lists: only the same stream is equal
+            LazyList(lazy) => match right {
+                LazyList(other) => Rc::ptr_eq(lazy, other),
+                _ => false,
+            },
             _ => false,
         }
     }
 
+    // name used for ErrorKind::Type
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Num(_) | BigNum(_) | Ratio(_) | Float(_) => "number",
+            Bool(_) => "boolean",
+            Str(_) => "string",
+            ListValue(_) => "list",
+            MapValue(_) => "map",
+            LazyList(_) => "lazy list",
+            AdtValue(_) => "data",
+            ErrorValue(_) => "error",
+            FunctionVariable(_) | SetFn(_) => "function",
+            SetVar(lr) => lr.value.type_name(),
+            ThunkValue(thunk) => thunk.value().map_or("thunk", |value| value.type_name()),
+            Unit => "unit",
+        }
+    }
+
+    // type error for when self isn't the expected type
+    pub fn type_error(&self, expected: &str, msg: &str) -> Ex {
+        Ex::new(msg).with_kind(ErrorKind::Type {
+            expected: expected.to_string(),
+            actual: self.type_name().to_string(),
+        })
+    }
+
     // expect functions
-    // cast bool to 0/1
     pub fn expect_num(&self) -> Result<NumType> {
         match self {
             Num(num) => Ok(*num),
-            Bool(b) => Ok(if *b { 1 } else { 0 }),
+            BigNum(big) => errf!(
+                "{} is too large, expected a number that fits in 64 bits.",
+                big
+            ),
+            Ratio(_) | Float(_) => {
+                let msg = format!("Expected an integer but got '{}'", self.to_string());
+                Err(self.type_error("integer", &msg))
+            }
             _ => {
                 let msg = format!("Expected a number but got '{}'", self.to_string());
-                err!(msg.as_str())
+                Err(self.type_error("number", &msg))
             }
         }
     }
 
+    // for if, and, or, not: 0, "" and [] are false too
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Num(num) => *num != 0,
+            Float(float) => *float != 0.0,
+            Bool(b) => *b,
+            Str(string) => !string.is_empty(),
+            ListValue(lst) => !lst.is_empty(),
+            _ => true,
+        }
+    }
+
     pub fn expect_bool(&self) -> Result<bool> {
         match self {
             Bool(bool) => Ok(*bool),
             _ => {
                 let msg = format!("Expected a boolean but got '{}'", self.to_string());
-                err!(msg.as_str())
+                Err(self.type_error("boolean", &msg))
+            }
+        }
+    }
+
+    pub fn expect_string(&self) -> Result<&String> {
+        match self {
+            Str(string) => Ok(string),
+            _ => {
+                let msg = format!("Expected a string but got '{}'", self.to_string());
+                Err(self.type_error("string", &msg))
+            }
+        }
+    }
+
+    pub fn expect_list(&self) -> Result<&Rc<Vec<DataValue>>> {
+        match self {
+            ListValue(lst) => Ok(lst),
+            _ => {
+                let msg = format!("Expected a list but got '{}'", self.to_string());
+                Err(self.type_error("list", &msg))
             }
         }
     }
@@ -94,7 +362,7 @@ impl DataValue {
             FunctionVariable(fn_ref) => Ok(fn_ref),
             _ => {
                 let msg = format!("Expected a function but got '{}'", self.to_string());
-                err!(msg.as_str())
+                Err(self.type_error("function", &msg))
             }
         }
     }
@@ -104,7 +372,7 @@ impl DataValue {
             &SetFn(fn_ref) => Ok(&fn_ref),
             _ => {
                 let msg = format!("Expected user function but got '{}'", self.to_string());
-                err!(msg.as_str())
+                Err(self.type_error("function", &msg))
             }
         }
     }
@@ -112,13 +380,44 @@ impl DataValue {
     pub fn to_string(&self) -> String {
         match self {
             Num(n) => n.to_string(),
+            BigNum(big) => big.to_string(),
+            Ratio(ratio) => ratio.to_string(),
+            Float(float) => format_float(*float),
             Bool(b) => b.to_string(),
+            Str(string) => string.clone(),
+            ListValue(lst) => {
+                let v: Vec<String> = lst.iter().map(|x| x.to_nested_string()).collect();
+                format!("{}{}{}", OPEN_LIST, v.join(VAR_SEP), CLOSE_LIST)
+            }
+            MapValue(map) => {
+                let v: Vec<String> = map
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{} {}", key.to_nested_string(), value.to_nested_string())
+                    })
+                    .collect();
+                format!("{}{}{}", OPEN_MAP, v.join(VAR_SEP), CLOSE_MAP)
+            }
+            LazyList(lazy) => lazy.to_string(),
+            AdtValue(adt) => adt.to_string(),
+            ErrorValue(err) => format!("{}: {}", err.kind.name(), err.message),
             FunctionVariable(f) => f.to_string(),
             SetVar(lr) => lr.value.to_string(),
             SetFn(func) => func.to_string(),
+            ThunkValue(thunk) => thunk
+                .value()
+                .map_or(String::from("..."), |value| value.to_string()),
             Unit => String::from(""),
         }
     }
+
+    // inside a list or map: strings keep their quotes, so "1" and 1 look different there
+    pub fn to_nested_string(&self) -> String {
+        match self {
+            Str(string) => escape_string(string),
+            _ => self.to_string(),
+        }
+    }
 }
 
 // expect_eval: takes one arg -> Option<DataValue> (None if uneval)
@@ -191,6 +490,7 @@ impl Display for Arg {
 pub enum ArgType {
     Evaluated,
     Unevaluated,
+    Deferred, // thunks of the args, from user functions in a lazy ctx
 }
 
 #[derive(PartialEq)]
@@ -219,7 +519,7 @@ pub mod tests {
         dbg!(d3.to_string());
 
         assert_eq!(d1.expect_num().unwrap(), 20);
-        assert!(d2.expect_num().unwrap().eq(&1));
+        assert!(d2.expect_num().is_err());
         assert!(d3.expect_num().is_err());
 
         assert!(d1.expect_bool().is_err());
@@ -229,6 +529,37 @@ pub mod tests {
         assert!(d1.expect_function().is_err());
         assert!(d2.expect_function().is_err());
         assert!(d3.expect_function().is_ok());
+
+        let d4 = ListValue(Rc::new(vec![Num(1), Bool(false)]));
+        assert_eq!(d4.to_string(), "[1,false]");
+        assert_eq!(d4.expect_list().unwrap().len(), 2);
+        assert!(d1.expect_list().is_err());
+    }
+
+    #[test]
+    fn data_test_strings() {
+        let s1 = Str(String::from("a b"));
+        assert_eq!(s1.to_string(), "a b");
+        assert_eq!(s1.expect_string().unwrap(), "a b");
+        assert!(Num(1).expect_string().is_err());
+
+        assert!(s1.equals(&Str(String::from("a b"))));
+        assert!(!s1.equals(&Str(String::from("a"))));
+        assert!(!Str(String::from("1")).equals(&Num(1)));
+    }
+
+    #[test]
+    fn data_test_list_equals() {
+        let l1 = ListValue(Rc::new(vec![Num(1), ListValue(Rc::new(vec![Num(2)]))]));
+        let l2 = ListValue(Rc::new(vec![Num(1), ListValue(Rc::new(vec![Num(2)]))]));
+        let l3 = ListValue(Rc::new(vec![Num(1), Num(2)]));
+        let nil = ListValue(Rc::new(vec![]));
+
+        assert!(l1.equals(&l2));
+        assert!(!l1.equals(&l3));
+        assert!(!l1.equals(&nil));
+        assert!(nil.equals(&ListValue(Rc::new(vec![]))));
+        assert!(!nil.equals(&Num(0)));
     }
 
     #[test]
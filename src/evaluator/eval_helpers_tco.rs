@@ -6,10 +6,12 @@ use std::collections::VecDeque;
 use crate::message::*;
 use crate::parser::parse_node::*;
 use crate::{
-    constants::{LET_NAME, STMT_END},
+    constants::{LET_NAME, MATCH_NAME, STMT_END},
     evaluator::function_tco::UserFunction,
 };
 
+use super::adt::{data_bindings, match_pattern};
+use super::builtins_tco::{build_try, BuiltIn};
 use super::evaluator_tco::*;
 use super::{context_tco::*, data_tco::*, function_tco::*};
 
@@ -64,13 +66,13 @@ pub fn get_eval_args_from_nodes(
     Ok(results)
 }
 
-// get args from results queue for func
-fn get_args(func: &FunctionCall, results: &mut VecDeque<ExpressionResult>) -> Vec<Arg> {
+// get args from results queue for the frame at index frame
+fn get_args(frame: usize, results: &mut VecDeque<ExpressionResult>) -> Vec<Arg> {
     let mut args: VecDeque<Arg> = VecDeque::new();
 
     // take from back of results queue until we encounter res with diff parent
     for res in results.iter().rev() {
-        if !can_resolve(func, &res.parent) {
+        if !can_resolve(frame, res.parent) {
             break;
         }
 
@@ -114,23 +116,25 @@ pub fn is_valid_identifier(s: &str) -> Result<String> {
     Ok(s)
 }
 
-// checks if func call ast and parent are the same
-pub fn can_resolve(fn_call: &FunctionCall, expr_parent: &Option<Rc<ASTNode>>) -> bool {
-    let fn_ast = &fn_call.ast;
-
-    match expr_parent {
-        Some(parent) => {
-            // check nodes directly
-            parent.as_ref().eq(fn_ast.as_ref())
+pub fn read_symbol(ctx: &EvalContext, sym: &String) -> Result<DataValue> {
+    match ctx.get(sym) {
+        Some(val) => Ok(val),
+        None => {
+            let err_string = format!("Unrecognised symbol: \"{}\"", sym);
+            let kind = ErrorKind::Name { name: sym.clone() };
+            Err(Ex::new(&err_string).with_kind(kind))
         }
-        None => false,
     }
 }
 
+// checks if expr_parent is the frame at index frame of fn_stack
+pub fn can_resolve(frame: usize, expr_parent: Option<usize>) -> bool {
+    expr_parent == Some(frame)
+}
+
 // for (def fn ...)
 pub fn resolve_fn_node(ctx: &EvalContext, fn_def: &FnDef, outer_call: bool) -> Result<DataValue> {
-    let func = UserFunction::new(ctx, &fn_def);
-    let rc: Rc<UserFunction> = Rc::new(func);
+    let rc = define_fns(ctx, &[fn_def]).pop().unwrap();
 
     if !outer_call {
         return Ok(FunctionVariable(rc));
@@ -140,35 +144,94 @@ pub fn resolve_fn_node(ctx: &EvalContext, fn_def: &FnDef, outer_call: bool) -> R
     Ok(SetFn(rc))
 }
 
+// globals already has every def made at the top level
+// the local defs of a body are a group that can call each other
+pub fn define_fns(ctx: &EvalContext, fn_defs: &[&FnDef]) -> Vec<Rc<UserFunction>> {
+    if ctx.is_global() {
+        return fn_defs
+            .iter()
+            .map(|fn_def| Rc::new(UserFunction::new(ctx, fn_def)))
+            .collect();
+    }
+
+    UserFunction::new_group(ctx, fn_defs)
+        .into_iter()
+        .map(Rc::new)
+        .collect()
+}
+
+// (let a 1 b 2 body): the values are evaluated one at a time on the stacks,
+// with a Bind frame waiting to add each one to the let's own ctx
 pub fn resolve_let(
-    ctx: &EvalContext,
-    expressions: &Vec<Rc<ASTNode>>,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    args: ResolveExprArgs,
     global: bool,
-) -> Result<DataValue> {
-    let mut new_ctx = ctx.copy(); // copy, not clone
-    let n = expressions.len();
-
-    let mut var: Option<&str> = None;
-    let mut outer_res: Option<DataValue> = None;
-
-    for (idx, nxt_node) in expressions.into_iter().enumerate() {
-        if idx == n - 1 {
-            let res = evaluate_outer(new_ctx.clone(), Rc::clone(nxt_node), false)?;
-            if let Some(var_name) = var {
-                new_ctx.write().add_variable(var_name, res.clone());
-            }
+) -> Result<()> {
+    if args.children.is_empty() {
+        let msg = format!("'{}' received nothing to evaluate.", LET_NAME);
+        return err!(&msg);
+    }
 
-            outer_res.replace(res);
-            continue;
+    let frame = FunctionCall {
+        next: Continuation::Bind {
+            idx: 0,
+            var: None,
+            global,
+        },
+        ast: Rc::clone(args.ast),
+        parent: args.parent,
+        context: args.ctx.copy(), // copy, not clone
+    };
+
+    next_binding(frame, 0, global, call_stack, fn_stack)
+}
+
+fn let_children(ast: &ASTNode) -> &Vec<Rc<ASTNode>> {
+    match &ast.value {
+        LetNode(children, _) => children,
+        _ => unreachable!(),
+    }
+}
+
+// just the names a global let bound, to be written into the outer ctx
+fn let_bindings(ctx: &EvalContext, ast: &ASTNode) -> EvalContext {
+    let children = let_children(ast);
+    let mut bound = Context::new();
+
+    for name in children.iter().take(children.len() - 1).step_by(2) {
+        let name = name.get_symbol().unwrap();
+        if let Some(value) = ctx.read().get_data_value(&name) {
+            bound.add_variable(&name, value.clone());
         }
+    }
 
-        // assign result to var name
-        if var.is_some() {
-            let res = evaluate_outer(new_ctx.clone(), Rc::clone(nxt_node), false)?;
-            outer_res.replace(res.clone());
-            new_ctx.write().add_variable(var.unwrap(), res);
-            var.take();
-            continue;
+    EvalContext::new_from_context(&bound)
+}
+
+// check names from idx until the next value, then wait for it
+fn next_binding(
+    mut frame: FunctionCall,
+    mut idx: usize,
+    global: bool,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+) -> Result<()> {
+    let ast = Rc::clone(&frame.ast);
+    let children = let_children(&ast);
+    let n = children.len();
+    let mut var: Option<String> = None;
+
+    // the last child is always a value, so this ends by pushing one
+    loop {
+        let nxt_node = children.get(idx).unwrap();
+
+        if idx == n - 1 || var.is_some() {
+            frame.next = Continuation::Bind { idx, var, global };
+            let ctx = frame.context.clone();
+            fn_stack.push_back(frame);
+            push_child(call_stack, fn_stack, &ctx, nxt_node);
+            return Ok(());
         }
 
         match &nxt_node.value {
@@ -178,7 +241,7 @@ pub fn resolve_let(
                     return err!(msg);
                 }
                 let _check = is_valid_identifier(string.as_str())?;
-                var.replace(string.as_str());
+                var.replace(string.clone());
             }
             _ => {
                 let msg = format!(
@@ -189,31 +252,73 @@ pub fn resolve_let(
                 return err!(&msg);
             }
         }
+
+        idx += 1;
     }
+}
 
-    if outer_res.is_none() {
-        let msg = format!("'{}' received nothing to evaluate.", LET_NAME);
-        return err!(&msg);
+// a let value has been evaluated: bind it, then go on with the next one or finish
+fn resume_let(
+    frame: &FunctionCall,
+    value: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) -> Result<()> {
+    let (idx, var, global) = match &frame.next {
+        Continuation::Bind { idx, var, global } => (*idx, var, *global),
+        _ => unreachable!(),
+    };
+
+    let mut new_ctx = frame.context.clone();
+    if let Some(var_name) = var {
+        new_ctx.write().add_variable(var_name, value.clone());
     }
 
-    let res = outer_res.unwrap();
+    if idx + 1 < let_children(&frame.ast).len() {
+        return next_binding(frame.clone(), idx + 1, global, call_stack, fn_stack);
+    }
 
-    if !global {
-        Ok(res)
+    let data = if global {
+        SetVar(LetReturn::new(let_bindings(&new_ctx, &frame.ast), value))
     } else {
-        let data = LetReturn::new(new_ctx, res);
-        Ok(SetVar(data))
-    }
+        value
+    };
+
+    results.push_back(ExpressionResult {
+        data,
+        parent: frame.parent,
+    });
+    Ok(())
 }
 
 pub struct ResolveExprArgs<'a> {
     pub ast: &'a Rc<ASTNode>,           // ast for the function call
     pub children: &'a Vec<Rc<ASTNode>>, // all the children of the expression
     pub ctx: &'a EvalContext,
-    pub parent: &'a Option<Rc<ASTNode>>, // parent of the expression
+    pub parent: Option<usize>, // frame the value of the expression goes to
 }
 
-// unroll expression onto call stack and resolve first member to a function then push to fn_stack
+// defer child for the frame on top of fn_stack
+fn push_child(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &VecDeque<FunctionCall>,
+    ctx: &EvalContext,
+    child: &Rc<ASTNode>,
+) {
+    let deferred = DeferredExpression {
+        ctx: ctx.clone(),
+        body: Rc::clone(child),
+    };
+    let stack_expr = StackExpression {
+        expr: deferred,
+        parent: Some(fn_stack.len() - 1),
+    };
+    call_stack.push_back(stack_expr);
+}
+
+// resolve first member to a function, then unroll the expression onto the stacks
+// a symbol is looked up directly, anything else gets an Operator frame waiting for its value
 pub fn resolve_expression(
     call_stack: &mut VecDeque<StackExpression>,
     fn_stack: &mut VecDeque<FunctionCall>,
@@ -222,7 +327,6 @@ pub fn resolve_expression(
 ) -> Result<()> {
     let children = args.children;
     let ctx = args.ctx;
-    let parent = args.parent;
     let ast = args.ast;
 
     if children.is_empty() {
@@ -230,32 +334,89 @@ pub fn resolve_expression(
     }
 
     let first_child = children.first().unwrap();
-    // recursively evaluate function call
-    let eval_first = evaluate_outer(ctx.clone(), Rc::clone(first_child), false)?;
+    if let Symbol(sym) = &first_child.value {
+        match read_symbol(ctx, sym)? {
+            // not forced yet: evaluate it as an operator below
+            ThunkValue(thunk) if thunk.value().is_none() => {}
+            ThunkValue(thunk) => {
+                let func = thunk.value().unwrap();
+                return push_call(call_stack, fn_stack, results, args, func);
+            }
+            func => return push_call(call_stack, fn_stack, results, args, func),
+        }
+    }
+
+    let frame = FunctionCall {
+        next: Continuation::Operator,
+        ast: Rc::clone(ast),
+        parent: args.parent,
+        context: ctx.clone(),
+    };
+
+    fn_stack.push_back(frame);
+    push_child(call_stack, fn_stack, ctx, first_child);
+    Ok(())
+}
+
+// first member of the expression in frame.ast has been evaluated
+fn resume_operator(
+    frame: &FunctionCall,
+    value: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) -> Result<()> {
+    let args = ResolveExprArgs {
+        children: frame.ast.get_children().unwrap(),
+        ctx: &frame.context,
+        parent: frame.parent,
+        ast: &frame.ast,
+    };
+
+    push_call(call_stack, fn_stack, results, args, value)
+}
+
+// push the call to first (evaluated) with the rest of the children as args
+fn push_call(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+    args: ResolveExprArgs,
+    first: DataValue,
+) -> Result<()> {
+    let children = args.children;
+    let ctx = args.ctx;
+    let ast = args.ast;
 
     // we expect first part of expression to resolve to a fn call
     // let and if handled separately already
-    let func = eval_first.expect_function()?;
+    let func = first.expect_function()?;
     let func_call = FunctionCall {
-        func: func.clone(),
+        next: Continuation::Call(func.clone()),
         ast: Rc::clone(ast),
-        parent: parent.clone(),
+        parent: args.parent,
         context: ctx.clone(),
     };
 
     // push rest of child expressions onto call_st
-    let mut rest_children = children.into_iter();
-    rest_children.next(); // go past first
+    let rest_children = children.iter().skip(1);
 
     // uneval: dont use stack, pass args directly to function
     if func.get_arg_type().eq(&ArgType::Unevaluated) {
         let args: Vec<Arg> = rest_children.map(|x| Unevaluated(Rc::clone(x))).collect();
-        return evaluate_fn(args, &func_call, call_stack, results, fn_stack);
+        return evaluate_fn(&func, args, &func_call, call_stack, results, fn_stack);
     }
 
-    fn_stack.push_back(func_call);
+    // by need: nothing is evaluated until the function reads it
+    if func.get_arg_type().eq(&ArgType::Deferred) {
+        let args: Vec<Arg> = rest_children
+            .filter(|x| !x.is_unit())
+            .map(|x| Evaluated(delay(ctx, x)))
+            .collect();
+        return evaluate_fn(func, args, &func_call, call_stack, results, fn_stack);
+    }
 
-    // update_max_len_fn(fn_stack.len());
+    fn_stack.push_back(func_call);
 
     // push in reverse
     for child in rest_children.rev() {
@@ -263,56 +424,400 @@ pub fn resolve_expression(
             continue;
         }
 
-        let deferred = DeferredExpression {
+        push_child(call_stack, fn_stack, ctx, child);
+    }
+
+    Ok(())
+}
+
+// literals and symbols that are already bound don't need a thunk
+fn delay(ctx: &EvalContext, node: &Rc<ASTNode>) -> DataValue {
+    let value = match &node.value {
+        Number(n) => Some(Num(*n)),
+        BigNumber(big) => Some(BigNum(Rc::clone(big))),
+        Fraction(ratio) => Some(Ratio(Rc::clone(ratio))),
+        Decimal(float) => Some(Float(*float)),
+        Boolean(b) => Some(Bool(*b)),
+        StringLiteral(string) => Some(Str(string.clone())),
+        Symbol(sym) => ctx.get(sym),
+        _ => None,
+    };
+
+    value.unwrap_or_else(|| {
+        let expr = DeferredExpression {
             ctx: ctx.clone(),
-            body: Rc::clone(child),
+            body: Rc::clone(node),
         };
-        let stack_expr = StackExpression {
-            expr: deferred,
-            parent: Some(Rc::clone(ast)),
+        ThunkValue(Rc::new(Thunk::new(expr)))
+    })
+}
+
+// evaluate the thunk in a Force frame the first time, after that it is just its value
+pub fn force_thunk(
+    thunk: Rc<Thunk>,
+    ast: &Rc<ASTNode>,
+    parent: Option<usize>,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) {
+    let expr = match thunk.expression() {
+        Some(expr) => expr,
+        None => {
+            let data = thunk.value().unwrap();
+            results.push_back(ExpressionResult { data, parent });
+            return;
+        }
+    };
+
+    let frame = FunctionCall {
+        next: Continuation::Force(thunk),
+        ast: Rc::clone(ast),
+        parent,
+        context: expr.ctx.clone(),
+    };
+    fn_stack.push_back(frame);
+
+    call_stack.push_back(StackExpression {
+        expr,
+        parent: Some(fn_stack.len() - 1),
+    });
+}
+
+// [a b c] or {k v}: elements go on the call stack like args, collected by the literal builtin
+// on fn_stack. empty is the value with no elements, which doesn't need the builtin
+pub fn resolve_list(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+    args: ResolveExprArgs,
+    literal: BuiltIn,
+    empty: DataValue,
+) -> Result<()> {
+    let children = args.children;
+    let ctx = args.ctx;
+    let parent = args.parent;
+    let ast = args.ast;
+
+    if children.is_empty() {
+        let expr_res = ExpressionResult {
+            data: empty,
+            parent,
         };
+        results.push_back(expr_res);
+        return Ok(());
+    }
 
-        // assigned parent to one level above supposed to be
+    let func_call = FunctionCall {
+        next: Continuation::Call(Rc::new(literal)),
+        ast: Rc::clone(ast),
+        parent,
+        context: ctx.clone(),
+    };
 
-        call_stack.push_back(stack_expr);
-        // update_max_len(call_stack.len());
+    fn_stack.push_back(func_call);
+
+    // push in reverse
+    for child in children.iter().rev() {
+        push_child(call_stack, fn_stack, ctx, child);
     }
 
     Ok(())
 }
 
-pub fn evaluate_if(ctx: &EvalContext, children: &Vec<Rc<ASTNode>>) -> Result<DeferredExpression> {
-    // recursive eval: real recursion
-    let cond = children.get(0).unwrap();
-    let e1 = children.get(1).unwrap();
-    let e2 = children.get(2).unwrap();
+// (try body (catch e handler)): the try gets a frame on fn_stack which just passes the
+// body's value on, so the handler knows where to unwind to and when the body is done
+pub fn resolve_try(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+    args: ResolveExprArgs,
+) -> Result<TryHandler> {
+    let children = args.children;
+    let ctx = args.ctx;
+    let parent = args.parent;
+    let ast = args.ast;
+
+    let body = children.get(0).unwrap();
+    let name = children.get(1).unwrap().get_symbol().unwrap();
+    let handler_body = children.get(2).unwrap();
 
-    let cond_result = evaluate_outer(ctx.clone(), Rc::clone(cond), false)?;
+    // is_func would return the frame itself instead of the value
+    let mut frame_ast = ast.copy();
+    frame_ast.is_func = false;
 
-    // add empty list as false later
-    let condition = match cond_result {
-        Num(num) => num != 0,
-        Bool(b) => b,
-        _ => true,
+    let func_call = FunctionCall {
+        next: Continuation::Call(Rc::new(build_try())),
+        ast: Rc::new(frame_ast),
+        parent,
+        context: ctx.clone(),
     };
 
-    if condition {
-        Ok(DeferredExpression {
+    let call_len = call_stack.len();
+    let results_len = results.len();
+    fn_stack.push_back(func_call);
+    push_child(call_stack, fn_stack, ctx, body);
+
+    Ok(TryHandler {
+        call_len,
+        fn_len: fn_stack.len(),
+        results_len,
+        name,
+        handler: DeferredExpression {
             ctx: ctx.clone(),
-            body: Rc::new(e1.as_ref().clone()),
-        })
+            body: Rc::clone(handler_body),
+        },
+        parent,
+    })
+}
+
+// (if cond e1 e2): a Branch frame waits for cond, then the branch goes on in the if's place
+pub fn resolve_if(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    args: ResolveExprArgs,
+) {
+    let frame = FunctionCall {
+        next: Continuation::Branch,
+        ast: Rc::clone(args.ast),
+        parent: args.parent,
+        context: args.ctx.clone(),
+    };
+
+    fn_stack.push_back(frame);
+    push_child(
+        call_stack,
+        fn_stack,
+        args.ctx,
+        args.children.first().unwrap(),
+    );
+}
+
+fn resume_if(
+    frame: &FunctionCall,
+    cond_result: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+) {
+    let children = match &frame.ast.value {
+        IfNode(children) => children,
+        _ => unreachable!(),
+    };
+
+    let branch = if cond_result.is_truthy() {
+        children.get(1).unwrap()
     } else {
-        Ok(DeferredExpression {
-            ctx: ctx.clone(),
-            body: Rc::new(e2.as_ref().clone()),
+        children.get(2).unwrap()
+    };
+
+    let stack_expr = StackExpression {
+        expr: DeferredExpression {
+            ctx: frame.context.clone(),
+            body: Rc::clone(branch),
+        },
+        parent: frame.parent,
+    };
+    call_stack.push_back(stack_expr);
+}
+
+// (match value (pattern body) ...): a Match frame waits for the value, then the body of
+// the first pattern that fits goes on in the match's place
+pub fn resolve_match(
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    args: ResolveExprArgs,
+) {
+    let frame = FunctionCall {
+        next: Continuation::Match,
+        ast: Rc::clone(args.ast),
+        parent: args.parent,
+        context: args.ctx.clone(),
+    };
+
+    fn_stack.push_back(frame);
+    push_child(
+        call_stack,
+        fn_stack,
+        args.ctx,
+        args.children.first().unwrap(),
+    );
+}
+
+fn resume_match(
+    frame: &FunctionCall,
+    value: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+) -> Result<()> {
+    let children = match &frame.ast.value {
+        MatchNode(children) => children,
+        _ => unreachable!(),
+    };
+
+    for clause in children[1..].chunks(2) {
+        let mut bindings: Vec<(String, DataValue)> = vec![];
+        if !match_pattern(&frame.context, &clause[0], &value, &mut bindings)? {
+            continue;
+        }
+
+        let mut ctx = frame.context.copy();
+        for (name, bound) in bindings {
+            ctx.write().add_variable(&name, bound);
+        }
+
+        let stack_expr = StackExpression {
+            expr: DeferredExpression {
+                ctx,
+                body: Rc::clone(&clause[1]),
+            },
+            parent: frame.parent,
+        };
+        call_stack.push_back(stack_expr);
+        return Ok(());
+    }
+
+    let msg = format!(
+        "'{}' has no pattern for '{}'.",
+        MATCH_NAME,
+        value.to_string()
+    );
+    Err(Ex::new(&msg).with_kind(ErrorKind::Match))
+}
+
+fn sequence_of(frame: &FunctionCall) -> (Rc<Sequence>, Rc<dyn Function>) {
+    match &frame.next {
+        Continuation::Sequence { seq, func, .. } => (Rc::clone(seq), Rc::clone(func)),
+        _ => unreachable!(),
+    }
+}
+
+fn has_def(statements: &[Rc<ASTNode>]) -> bool {
+    statements
+        .iter()
+        .any(|node| matches!(node.value, FnNode(_)))
+}
+
+// a binding made after the defs: make the group again so it sees the binding too
+fn redefine_body_fns(ctx: &mut EvalContext, seq: &Sequence, idx: usize) {
+    if has_def(&seq.statements()[..idx]) {
+        define_body_fns(ctx, seq.statements());
+    }
+}
+
+fn define_body_fns(ctx: &mut EvalContext, statements: &[Rc<ASTNode>]) {
+    let fn_defs: Vec<&FnDef> = statements
+        .iter()
+        .filter_map(|node| match &node.value {
+            FnNode(fn_def) => Some(fn_def),
+            _ => None,
         })
+        .collect();
+
+    for func in define_fns(ctx, &fn_defs) {
+        let name = func.get_name();
+        ctx.write().add_function(&name, func);
+    }
+}
+
+// a function body or chain: the frame waits for each statement in turn, from idx
+fn next_statement(
+    mut frame: FunctionCall,
+    mut idx: usize,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) -> Result<()> {
+    let (seq, func) = sequence_of(&frame);
+    while let Some(node) = seq.statements().get(idx) {
+        // def needs no evaluation, let binds into ctx once its SetVar comes back
+        // constructors are added like defs
+        if let (DataNode(data_def), true) = (&node.value, seq.bind) {
+            frame.context.write_context(data_bindings(data_def));
+            redefine_body_fns(&mut frame.context, &seq, idx);
+            idx += 1;
+            continue;
+        }
+
+        // the first def makes every def in the body, so they can all refer to each other
+        if let (FnNode(_), true) = (&node.value, seq.bind) {
+            if !has_def(&seq.statements()[..idx]) {
+                define_body_fns(&mut frame.context, seq.statements());
+            }
+            idx += 1;
+            continue;
+        }
+
+        frame.next = Continuation::Sequence {
+            seq: Rc::clone(&seq),
+            idx,
+            func,
+        };
+        let ctx = frame.context.clone();
+        fn_stack.push_back(frame);
+
+        if let (LetNode(children, _), true) = (&node.value, seq.bind) {
+            let args = ResolveExprArgs {
+                children,
+                ctx: &ctx,
+                parent: Some(fn_stack.len() - 1),
+                ast: node,
+            };
+            return resolve_let(call_stack, fn_stack, args, true);
+        }
+
+        push_child(call_stack, fn_stack, &ctx, node);
+        return Ok(());
+    }
+
+    match seq.get_tail() {
+        Some(tail) => {
+            let stack_expr = StackExpression {
+                expr: DeferredExpression {
+                    ctx: frame.context,
+                    body: Rc::clone(tail),
+                },
+                parent: frame.parent,
+            };
+            call_stack.push_back(stack_expr);
+        }
+        None => {
+            let expr_res = ExpressionResult {
+                data: DataValue::Unit,
+                parent: frame.parent,
+            };
+            results.push_back(expr_res);
+        }
+    }
+
+    Ok(())
+}
+
+fn resume_sequence(
+    frame: &FunctionCall,
+    value: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) -> Result<()> {
+    let idx = match &frame.next {
+        Continuation::Sequence { idx, .. } => *idx,
+        _ => unreachable!(),
+    };
+
+    let mut frame = frame.clone();
+    let seq = sequence_of(&frame).0;
+    if let (SetVar(data), true) = (value, seq.bind) {
+        frame.context.write_context(*data.context);
+        redefine_body_fns(&mut frame.context, &seq, idx);
     }
+
+    next_statement(frame, idx + 1, call_stack, fn_stack, results)
 }
+
 // change: func.execute should always return a curried function
 // func.resolve forces the function to return the actual value
 // for finite: return the function as is if args < needed, else return the eval value
 // inf: return func if args < min, else return eval value
 pub fn evaluate_fn(
+    func: &Rc<dyn Function>,
     args: Vec<Arg>,
     func_call: &FunctionCall,
     call_stack: &mut VecDeque<StackExpression>,
@@ -330,11 +835,11 @@ pub fn evaluate_fn(
     // 2. if func.ast NOT is_func (result expected to be final result) -> call func.resolve
     // put on call_stack or result as normal
 
-    let func = func_call.func.apply(&args);
+    let func = func.apply(&args);
     if func_call.ast.is_func {
         let expr_res = ExpressionResult {
             data: FunctionVariable(func),
-            parent: func_call.parent.clone(), // cloning the OPTION - should be same id
+            parent: func_call.parent,
         };
 
         results.push_back(expr_res);
@@ -351,7 +856,7 @@ pub fn evaluate_fn(
         DeferredExpr(def) => {
             let stack_expr = StackExpression {
                 expr: def,
-                parent: func_call.parent.clone(), // cloning the OPTION
+                parent: func_call.parent,
             };
             call_stack.push_back(stack_expr);
         }
@@ -360,23 +865,170 @@ pub fn evaluate_fn(
         EvaluatedExpr(ev) => {
             let expr_res = ExpressionResult {
                 data: ev,
-                parent: func_call.parent.clone(), // cloning the OPTION - should be same id
+                parent: func_call.parent,
             };
             results.push_back(expr_res);
         }
+
+        // statements first, on the stacks: the frame takes the place of the call
+        WaitExpr(expr, next) => {
+            wait_on(
+                expr,
+                next,
+                &func_call.ast,
+                func_call.parent,
+                call_stack,
+                fn_stack,
+            );
+        }
+
+        SequenceExpr(seq) => {
+            let frame = FunctionCall {
+                context: seq.ctx.clone(),
+                next: Continuation::Sequence {
+                    seq: Rc::new(seq),
+                    idx: 0,
+                    func,
+                },
+                ast: Rc::clone(&func_call.ast),
+                parent: func_call.parent,
+            };
+            next_statement(frame, 0, call_stack, fn_stack, results)?;
+        }
     }
 
     Ok(())
 }
 
+// a frame for next, waiting for expr in the place of the call in ast
+fn wait_on(
+    expr: DeferredExpression,
+    next: Continuation,
+    ast: &Rc<ASTNode>,
+    parent: Option<usize>,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+) {
+    let frame = FunctionCall {
+        next,
+        ast: Rc::clone(ast),
+        parent,
+        context: expr.ctx.clone(),
+    };
+    fn_stack.push_back(frame);
+
+    let stack_expr = StackExpression {
+        expr,
+        parent: Some(fn_stack.len() - 1),
+    };
+    call_stack.push_back(stack_expr);
+}
+
+// (and a b c)/(or a b c): evaluated one at a time, the last is the tail
+pub fn next_short_circuit(
+    ctx: &EvalContext,
+    args: Rc<Vec<Rc<ASTNode>>>,
+    idx: usize,
+    stop_at: bool,
+) -> Expression {
+    let expr = DeferredExpression {
+        ctx: ctx.clone(),
+        body: Rc::clone(&args[idx]),
+    };
+
+    if idx == args.len() - 1 {
+        return DeferredExpr(expr);
+    }
+    WaitExpr(expr, Continuation::ShortCircuit { args, idx, stop_at })
+}
+
+fn resume_short_circuit(
+    frame: &FunctionCall,
+    value: DataValue,
+    call_stack: &mut VecDeque<StackExpression>,
+    fn_stack: &mut VecDeque<FunctionCall>,
+    results: &mut VecDeque<ExpressionResult>,
+) {
+    let (args, idx, stop_at) = match &frame.next {
+        Continuation::ShortCircuit { args, idx, stop_at } => (Rc::clone(args), *idx, *stop_at),
+        _ => unreachable!(),
+    };
+
+    if value.is_truthy() == stop_at {
+        let expr_res = ExpressionResult {
+            data: value,
+            parent: frame.parent,
+        };
+        results.push_back(expr_res);
+        return;
+    }
+
+    match next_short_circuit(&frame.context, args, idx + 1, stop_at) {
+        WaitExpr(expr, next) => wait_on(expr, next, &frame.ast, frame.parent, call_stack, fn_stack),
+        DeferredExpr(expr) => call_stack.push_back(StackExpression {
+            expr,
+            parent: frame.parent,
+        }),
+        _ => unreachable!(),
+    }
+}
+
 // call function that takes evaluated arguments (args are on the res_q)
+// or go on with the special form waiting for a value
 pub fn call_fn_evaluated(
     fn_stack: &mut VecDeque<FunctionCall>,
     call_stack: &mut VecDeque<StackExpression>,
     results: &mut VecDeque<ExpressionResult>,
 ) -> Result<()> {
-    let func = &fn_stack.pop_back().unwrap();
-    let args = get_args(func, results);
+    let frame = fn_stack.pop_back().unwrap();
+    let args = get_args(fn_stack.len(), results);
+
+    let res = match &frame.next {
+        Continuation::Call(func) => evaluate_fn(func, args, &frame, call_stack, results, fn_stack),
+        next => {
+            // every child of a special form gives exactly one value, unit included
+            let value = args.into_iter().next().unwrap().expect_eval()?;
+            match next {
+                Continuation::Operator => {
+                    resume_operator(&frame, value, call_stack, fn_stack, results)
+                }
+                Continuation::Branch => {
+                    resume_if(&frame, value, call_stack);
+                    Ok(())
+                }
+                Continuation::Match => resume_match(&frame, value, call_stack),
+                Continuation::LazyHead(tail) => {
+                    let lazy = LazyCons::new(value, tail.clone());
+                    let expr_res = ExpressionResult {
+                        data: LazyList(Rc::new(lazy)),
+                        parent: frame.parent,
+                    };
+                    results.push_back(expr_res);
+                    Ok(())
+                }
+                Continuation::ShortCircuit { .. } => {
+                    resume_short_circuit(&frame, value, call_stack, fn_stack, results);
+                    Ok(())
+                }
+                Continuation::Bind { .. } => {
+                    resume_let(&frame, value, call_stack, fn_stack, results)
+                }
+                Continuation::Sequence { .. } => {
+                    resume_sequence(&frame, value, call_stack, fn_stack, results)
+                }
+                Continuation::Force(thunk) => {
+                    thunk.set(value.clone());
+                    let expr_res = ExpressionResult {
+                        data: value,
+                        parent: frame.parent,
+                    };
+                    results.push_back(expr_res);
+                    Ok(())
+                }
+                Continuation::Call(_) => unreachable!(),
+            }
+        }
+    };
 
-    evaluate_fn(args, func, call_stack, results, fn_stack)
+    res.map_err(|ex| call_failed(ex, &frame))
 }
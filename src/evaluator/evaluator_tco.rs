@@ -5,6 +5,10 @@ use std::collections::VecDeque;
 use crate::message::*;
 use crate::parser::parse_node::*;
 
+use super::adt::resolve_data_node;
+use super::builtins_tco::{build_list_literal, build_map_literal};
+use super::limits::{check_budget, check_interrupt};
+use super::map::MapData;
 use super::{context_tco::*, data_tco::*, eval_helpers_tco::*, function_tco::*};
 
 // FnDef: returns ExpressionResult
@@ -19,6 +23,8 @@ use super::{context_tco::*, data_tco::*, eval_helpers_tco::*, function_tco::*};
 pub enum Expression {
     DeferredExpr(DeferredExpression),
     EvaluatedExpr(DataValue),
+    SequenceExpr(Sequence),
+    WaitExpr(DeferredExpression, Continuation), // evaluated on the stacks, the frame goes on with it
 }
 
 impl Expression {
@@ -39,12 +45,69 @@ impl Expression {
 
 pub use Expression::*;
 
+// statements evaluated in order for their effects, then the tail is deferred in the same ctx
+// function bodies bind let and def statements into ctx, chain doesn't
+#[derive(Clone)]
+pub struct Sequence {
+    pub ctx: EvalContext,
+    pub body: Rc<Vec<Rc<ASTNode>>>, // shared with the function, not cloned
+    pub tail: bool,                 // last node is the tail, otherwise the result is unit
+    pub bind: bool,
+}
+
+impl Sequence {
+    pub fn statements(&self) -> &[Rc<ASTNode>] {
+        if self.tail {
+            &self.body[..self.body.len() - 1]
+        } else {
+            &self.body
+        }
+    }
+
+    pub fn get_tail(&self) -> Option<&Rc<ASTNode>> {
+        if self.tail {
+            self.body.last()
+        } else {
+            None
+        }
+    }
+}
+
+// what a frame on fn_stack does once the values it waits for are on the results queue
+// everything except Call waits for exactly one value
+#[derive(Clone)]
+pub enum Continuation {
+    Call(Rc<dyn Function>), // apply the function to the values
+    Operator,               // first child of ast: call what it evaluated to
+    Branch,                 // condition of the if in ast: go on with a branch
+    Match,                  // value the match in ast is on: go on with the body that fits
+    Bind {
+        idx: usize,          // child of the let in ast being evaluated
+        var: Option<String>, // name to bind the value to
+        global: bool,
+    },
+    Sequence {
+        seq: Rc<Sequence>,
+        idx: usize,             // statement being evaluated
+        func: Rc<dyn Function>, // whose body (or chain) this is, for traces
+    },
+    Force(Rc<Thunk>),             // memoize the value of the thunk
+    LazyHead(DeferredExpression), // head of an lcons: make the cell with this tail
+    ShortCircuit {
+        args: Rc<Vec<Rc<ASTNode>>>, // of the and/or in ast
+        idx: usize,                 // arg being evaluated, never the last: that is the tail
+        stop_at: bool,              // false for and, true for or
+    },
+}
+
 // function call on the call stack
+// parent everywhere is the index in fn_stack of the frame the value goes to,
+// None for the value of the whole evaluation
 #[derive(Clone)]
 pub struct FunctionCall {
-    pub func: Rc<dyn Function>,
+    pub next: Continuation,
     pub ast: Rc<ASTNode>,
-    pub parent: Option<Rc<ASTNode>>,
+    pub parent: Option<usize>,
     pub context: EvalContext,
 }
 
@@ -54,7 +117,18 @@ pub struct FunctionCall {
 // ensuring that a deferredexpr without a parent is invalid and wont go on the stack
 pub struct StackExpression {
     pub expr: DeferredExpression,
-    pub parent: Option<Rc<ASTNode>>,
+    pub parent: Option<usize>,
+}
+
+// a try waiting for its body: the stack sizes to unwind to if the body fails
+// fn_len includes the try's own frame, so the handler is done once fn_stack is shorter
+pub struct TryHandler {
+    pub call_len: usize,
+    pub fn_len: usize,
+    pub results_len: usize,
+    pub name: String,
+    pub handler: DeferredExpression,
+    pub parent: Option<usize>,
 }
 
 // echo(echo(1) + echo(2)) + echo(echo(4) + echo(5));
@@ -71,7 +145,7 @@ pub struct DeferredExpression {
 #[derive(Clone)]
 pub struct ExpressionResult {
     pub data: DataValue,
-    pub parent: Option<Rc<ASTNode>>,
+    pub parent: Option<usize>,
 }
 
 // (def recr (n) (if (eq n 0) 0 (add n (recr (pred n)))))
@@ -130,7 +204,7 @@ pub(crate) fn evaluate_outer(
 
     let stack_expr = StackExpression {
         expr: deferred,
-        parent: node.parent.clone(),
+        parent: None,
     };
 
     let res = evaluate_tco(stack_expr, outer_call);
@@ -145,6 +219,7 @@ fn resolve(
     call_stack: &mut VecDeque<StackExpression>,
     fn_stack: &mut VecDeque<FunctionCall>,
     results: &mut VecDeque<ExpressionResult>,
+    handlers: &mut Vec<TryHandler>,
     outer_call: bool,
 ) -> Result<()> {
     // pop from stack
@@ -154,11 +229,11 @@ fn resolve(
     let body = &expr.body;
     // println!("resolve_expr:{}", body.to_string_with_parent());
     let ctx = &expr.ctx;
-    let parent = &expression.parent; // dont use body.parent
+    let parent = expression.parent;
 
     let mut result = ExpressionResult {
         data: Num(-1),
-        parent: parent.clone(), // so I don't have to set parent repeatedly
+        parent, // so I don't have to set parent repeatedly
     };
 
     match &body.value {
@@ -166,32 +241,60 @@ fn resolve(
             result.data = Num(*n);
             results.push_back(result);
         }
+        BigNumber(big) => {
+            result.data = BigNum(Rc::clone(big));
+            results.push_back(result);
+        }
+        Fraction(ratio) => {
+            result.data = Ratio(Rc::clone(ratio));
+            results.push_back(result);
+        }
+        Decimal(float) => {
+            result.data = Float(*float);
+            results.push_back(result);
+        }
         Boolean(b) => {
             result.data = Bool(*b);
             results.push_back(result);
         }
-        Symbol(sym) => {
-            let read = ctx.read();
-            let value = read.get_data_value(&sym);
-
-            match value {
-                Some(val) => {
-                    result.data = val.clone();
-                    results.push_back(result);
-                }
-                None => {
-                    let err_string = format!("Unrecognised symbol: \"{}\"", sym);
-                    return err!(err_string.as_str());
-                }
-            }
+        StringLiteral(string) => {
+            result.data = Str(string.clone());
+            results.push_back(result);
         }
+        Symbol(sym) => match read_symbol(ctx, sym)? {
+            ThunkValue(thunk) => force_thunk(thunk, body, parent, call_stack, fn_stack, results),
+            value => {
+                result.data = value;
+                results.push_back(result);
+            }
+        },
         IfNode(children) => {
-            let res = evaluate_if(ctx, children)?;
-            let stack_expr = StackExpression {
-                expr: res,
-                parent: parent.clone(),
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
             };
-            call_stack.push_back(stack_expr);
+            resolve_if(call_stack, fn_stack, args);
+        }
+        MatchNode(children) => {
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
+            };
+            resolve_match(call_stack, fn_stack, args);
+        }
+        TryNode(children) => {
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
+            };
+            let handler = resolve_try(call_stack, fn_stack, results, args)?;
+            handlers.push(handler);
         }
         // only a side effect, no return (besides err)
         ParseExpression(children) => {
@@ -204,34 +307,123 @@ fn resolve(
             resolve_expression(call_stack, fn_stack, results, args)?;
         }
         LetNode(children, global) => {
-            let returned_result = resolve_let(&ctx, children, *global)?;
-            result.data = returned_result;
-            results.push_back(result);
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
+            };
+            resolve_let(call_stack, fn_stack, args, *global)?;
         }
         FnNode(fn_def) => {
             let fn_resolve = resolve_fn_node(&ctx, &fn_def, fn_def.global)?;
             result.data = fn_resolve;
             results.push_back(result);
         }
+        DataNode(data_def) => {
+            result.data = resolve_data_node(data_def)?;
+            results.push_back(result);
+        }
+        // never global: a lambda is always a value
+        LambdaNode(fn_def) => {
+            result.data = FunctionVariable(Rc::new(UserFunction::new(&ctx, fn_def)));
+            results.push_back(result);
+        }
         ParseUnit => {
             let u = DataValue::Unit;
             result.data = u;
             results.push_back(result);
         }
-        // List
-        _ => {
-            todo!()
+        List(children) => {
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
+            };
+            let empty = ListValue(Rc::new(vec![]));
+            resolve_list(
+                call_stack,
+                fn_stack,
+                results,
+                args,
+                build_list_literal(),
+                empty,
+            )?;
+        }
+        Map(children) => {
+            let args = ResolveExprArgs {
+                children,
+                ctx,
+                parent,
+                ast: body,
+            };
+            let empty = MapValue(Rc::new(MapData::new()));
+            resolve_list(
+                call_stack,
+                fn_stack,
+                results,
+                args,
+                build_map_literal(),
+                empty,
+            )?;
         }
     }
 
     Ok(())
 }
 
+// Traces: the call that failed, then the calls on fn_stack waiting for it
+// tail calls have already left fn_stack, and repeated frames are collapsed by Ex::add_frame
+// if, match and let frames aren't calls, so they are left out
+fn call_frame(call: &FunctionCall) -> Option<TraceFrame> {
+    let ast = &call.ast;
+    let name = match &call.next {
+        Continuation::Call(func) | Continuation::Sequence { func, .. } => func.get_name(),
+        Continuation::Operator => ast.get_ith_child(0).unwrap().to_string(),
+        Continuation::Branch
+        | Continuation::Match
+        | Continuation::Bind { .. }
+        | Continuation::Force(_)
+        | Continuation::LazyHead(_)
+        | Continuation::ShortCircuit { .. } => return None,
+    };
+
+    Some(TraceFrame::new(&name, &ast.to_string(), ast.span.clone()))
+}
+
+pub(crate) fn call_failed(ex: Ex, call: &FunctionCall) -> Ex {
+    let mut ex = ex.with_span(call.ast.span.clone());
+    if let Some(frame) = call_frame(call) {
+        ex.add_frame(frame);
+    }
+    ex
+}
+
+// only expressions that are calls go in the trace
+fn expression_failed(ex: Ex, body: &Rc<ASTNode>) -> Ex {
+    let mut ex = ex.with_span(body.span.clone());
+    if let ParseExpression(children) = &body.value {
+        let name = children.first().map(|x| x.to_string()).unwrap_or_default();
+        ex.add_frame(TraceFrame::new(&name, &body.to_string(), body.span.clone()));
+    }
+    ex
+}
+
+// error is leaving evaluate_tco: add the calls still waiting, innermost first
+fn with_trace(mut ex: Ex, fn_stack: &VecDeque<FunctionCall>) -> Ex {
+    for frame in fn_stack.iter().rev().filter_map(call_frame) {
+        ex.add_frame(frame);
+    }
+    ex
+}
+
 fn evaluate_tco(expression: StackExpression, outer_call: bool) -> Result<DataValue> {
     // try to match terminals
     let mut call_stack: VecDeque<StackExpression> = VecDeque::new();
     let mut fn_stack: VecDeque<FunctionCall> = VecDeque::new();
     let mut results_queue: VecDeque<ExpressionResult> = VecDeque::new();
+    let mut handlers: Vec<TryHandler> = vec![];
 
     let _max_len = 0;
 
@@ -241,52 +433,98 @@ fn evaluate_tco(expression: StackExpression, outer_call: bool) -> Result<DataVal
     call_stack.push_back(expression);
 
     // what to do with expression on call_st when valid
-    // valid: fn_st empty and call_st not empty OR fn_st[-1].ast==call_st[-1].parent
+    // valid: fn_st empty and call_st not empty OR call_st[-1].parent is the index of fn_st[-1]
 
     // call_st only: unroll the expr
     // fn_stack only: check res_q
-    // both: check fn_st[-1].ast vs call_st[-1].parent
+    // both: check fn_st[-1] index vs call_st[-1].parent
     while !call_stack.is_empty() || !fn_stack.is_empty() {
         // update_max_len(call_stack.len());
 
         let call_has = !call_stack.is_empty();
         let fn_has = !fn_stack.is_empty();
 
-        // both - check ast vs parent
-        if call_has && fn_has {
+        check_interrupt()
+            .and_then(|_| check_budget(call_stack.len(), fn_stack.len()))
+            .map_err(|ex| with_trace(ex, &fn_stack))?;
+
+        // both - check index vs parent
+        let step = if call_has && fn_has {
             let call_st_last = call_stack.back().unwrap();
-            let fn_st_last = fn_stack.back().unwrap();
 
-            if can_resolve(fn_st_last, &call_st_last.parent) {
+            if can_resolve(fn_stack.len() - 1, call_st_last.parent) {
+                let body = Rc::clone(&call_st_last.expr.body);
                 resolve(
                     &mut call_stack,
                     &mut fn_stack,
                     &mut results_queue,
+                    &mut handlers,
                     outer_call,
-                )?;
+                )
+                .map_err(|ex| expression_failed(ex, &body))
                 // update_max_len(call_stack.len());
 
                 // when call_stack[-1] doesnt match fn_st[-1]: evaluate
             } else {
-                call_fn_evaluated(&mut fn_stack, &mut call_stack, &mut results_queue)?;
+                call_fn_evaluated(&mut fn_stack, &mut call_stack, &mut results_queue)
             }
         }
         // call only: resolve whats on it
         else if call_has && !fn_has {
+            let body = Rc::clone(&call_stack.back().unwrap().expr.body);
             resolve(
                 &mut call_stack,
                 &mut fn_stack,
                 &mut results_queue,
+                &mut handlers,
                 outer_call,
-            )?;
+            )
+            .map_err(|ex| expression_failed(ex, &body))
             // update_max_len(call_stack.len());
         }
         // fn only - fn.execute
         // 1. get correct args from result queue
         // 2. pass to fn execute, get Expression
-        // 3. push onto res_q with correct parent=fn_ast.parent
+        // 3. push onto res_q with correct parent=fn.parent
         else {
-            call_fn_evaluated(&mut fn_stack, &mut call_stack, &mut results_queue)?;
+            call_fn_evaluated(&mut fn_stack, &mut call_stack, &mut results_queue)
+        };
+
+        // error: unwind to the innermost try, or give up if there isn't one
+        if let Err(ex) = step {
+            if let ErrorKind::Limit { .. } | ErrorKind::Interrupt = ex.get_kind() {
+                return Err(with_trace(ex, &fn_stack));
+            }
+
+            let handler = match handlers.pop() {
+                Some(handler) => handler,
+                None => return Err(with_trace(ex, &fn_stack)),
+            };
+
+            call_stack.truncate(handler.call_len);
+            fn_stack.truncate(handler.fn_len - 1); // the try's frame goes too
+            results_queue.truncate(handler.results_len);
+
+            let mut ctx = handler.handler.ctx.copy();
+            let err = ErrorValue(Rc::new(ErrorData::from_ex(&ex)));
+            ctx.write().add_variable(&handler.name, err);
+
+            let stack_expr = StackExpression {
+                expr: DeferredExpression {
+                    ctx,
+                    body: handler.handler.body,
+                },
+                parent: handler.parent,
+            };
+            call_stack.push_back(stack_expr);
+        }
+
+        // body finished without an error: its try frame has been popped
+        while let Some(handler) = handlers.last() {
+            if fn_stack.len() >= handler.fn_len {
+                break;
+            }
+            handlers.pop();
         }
 
         // update_max_len(call_stack.len());
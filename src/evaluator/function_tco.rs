@@ -27,11 +27,18 @@ pub trait Function {
 
     fn resolve(&self, context: &EvalContext) -> Result<Expression>;
 
+    fn get_name(&self) -> String;
+
     // default: Evaluated
     fn get_arg_type(&self) -> ArgType {
         ArgType::Evaluated
     }
 
+    // number of fields if this is a data constructor, for match
+    fn get_num_fields(&self) -> Option<usize> {
+        None
+    }
+
     // args so far for resolve
     // fn get_params(&self)->&Params;
 
@@ -43,6 +50,20 @@ pub trait Function {
 
 use crate::parser::parse_node::FnDef;
 
+// too many arguments for a function, shared by builtins and user functions
+pub fn arity_error(name: &str, expected: usize, received: usize) -> Ex {
+    let msg = format!(
+        "'{}' expected {} arguments but received {}.",
+        name, expected, received
+    );
+
+    Ex::new(&msg).with_kind(ErrorKind::Arity {
+        name: name.to_string(),
+        expected,
+        received,
+    })
+}
+
 // BuiltIn: name String, params:Params
 // name, params, body
 #[derive(Clone)]
@@ -50,7 +71,8 @@ pub struct UserFunction {
     context: EvalContext, // ctx at creation - user only
     name: String,         // b also
     params: Params,
-    body: Vec<Rc<ASTNode>>, // user only
+    body: Rc<Vec<Rc<ASTNode>>>,   // user only, shared by every call
+    group: Rc<Vec<UserFunction>>, // local defs made together, including this one: they can call each other
 }
 // clone fn_def because it could have come from a closure: the original function still needs it
 // same reason for context: to impl closure we need to capture ctx at time of creation
@@ -65,24 +87,53 @@ pub struct UserFunction {
 //
 impl UserFunction {
     pub fn new(context: &EvalContext, fn_def: &FnDef) -> UserFunction {
-        let mut stored_ctx = context.copy();
+        UserFunction::with_context(context.capture(), fn_def)
+    }
 
-        stored_ctx.write().delete_variable(&fn_def.name);
+    fn with_context(stored_ctx: EvalContext, fn_def: &FnDef) -> UserFunction {
         let params = fn_def.params.clone();
         let params = params.iter().map(|x| x.as_str()).collect();
 
         UserFunction {
-            context: stored_ctx, // copy to get new copy that doesn't affect
+            context: stored_ctx,
             name: fn_def.name.clone(),
             params: Params::new_finite(params),
             // params:fn_def.params.clone(),
             // params_idx:0,
-            body: fn_def.body.clone(), // ASTNode.clone
+            body: Rc::new(fn_def.body.clone()),
+            group: Rc::new(vec![]),
         }
     }
+
+    // letrec: every def sees all of them, the group is bound again on each call
+    // members stored in the group have an empty group of their own, so there's no Rc cycle
+    pub fn new_group(context: &EvalContext, fn_defs: &[&FnDef]) -> Vec<UserFunction> {
+        let stored_ctx = context.capture();
+        let members: Vec<UserFunction> = fn_defs
+            .iter()
+            .map(|fn_def| UserFunction::with_context(stored_ctx.clone(), fn_def))
+            .collect();
+
+        let group = Rc::new(members);
+        group.iter().map(|f| f.in_group(&group)).collect()
+    }
+
+    fn in_group(&self, group: &Rc<Vec<UserFunction>>) -> UserFunction {
+        let mut func = self.clone();
+        func.group = Rc::clone(group);
+        func
+    }
     // should be called at time of execution
+    // args go in a new frame over the context the function was defined in
     pub fn curry(&self, args: &[Arg]) -> Result<EvalContext> {
-        let mut new_ctx = EvalContext::new();
+        let mut new_ctx = self.context.copy();
+
+        // local defs aren't in globals, so the group is how they find each other
+        for member in self.group.iter() {
+            let func = Rc::new(member.in_group(&self.group));
+            new_ctx.write().add_function(member.name.as_str(), func);
+        }
+
         let eval_args = Arg::expect_all_eval(args)?;
         let num_args = eval_args.len();
 
@@ -100,13 +151,7 @@ impl UserFunction {
         // can't curry for too many
         if num_args > actual_params.len() {
             println!("gt_here");
-            let msg = format!(
-                "'{}' expected {} arguments but received {}.",
-                self.get_name(),
-                finite.params.len(),
-                num_args
-            );
-            return err!(msg);
+            return Err(arity_error(&self.get_name(), finite.params.len(), num_args));
         }
 
         // add args to context using params
@@ -119,8 +164,6 @@ impl UserFunction {
             new_ctx.write().add_variable(tup.0.as_str(), tup.1);
         });
 
-        let new_ctx = new_ctx.merge_context(&self.context);
-
         Ok(new_ctx)
     }
 
@@ -150,21 +193,24 @@ impl UserFunction {
 }
 
 impl Function for UserFunction {
+    fn get_arg_type(&self) -> ArgType {
+        match self.context.get_strategy() {
+            Strategy::Strict => ArgType::Evaluated,
+            Strategy::Lazy => ArgType::Deferred,
+        }
+    }
+
     fn resolve(&self, context: &EvalContext) -> Result<Expression> {
         match &self.params {
             Params::Finite(fin) => match fin.params_diff() {
                 // when less return curried function
                 Ordering::Less => Ok(EvaluatedExpr(FunctionVariable(Rc::new(self.clone())))),
                 Ordering::Equal => self.execute(&fin.received_args, context),
-                Ordering::Greater => {
-                    let msg = format!(
-                        "'{}' expected {} arguments but received {}.",
-                        self.name,
-                        fin.params.len(),
-                        fin.received_args.len()
-                    );
-                    err!(msg)
-                }
+                Ordering::Greater => Err(arity_error(
+                    &self.name,
+                    fin.params.len(),
+                    fin.received_args.len(),
+                )),
             },
             Params::Infinite(inf) => {
                 if inf.received_args.len() < inf.min {
@@ -181,41 +227,35 @@ impl Function for UserFunction {
         // let new_idx=self.params_idx+args.len();
         // let new_ctx=self.curry(args)?;
 
-        let new_body: Vec<Rc<ASTNode>> = self
-            .body
-            .iter()
-            .map(|node| node.as_ref().clone())
-            .map(|node| Rc::new(node))
-            .collect();
-
         let new_fn = UserFunction {
             context: self.context.clone(),
             name: self.name.clone(),
             params: self.params.apply(args),
-            body: new_body,
+            body: Rc::clone(&self.body),
+            group: Rc::clone(&self.group),
         };
 
         Rc::new(new_fn)
     }
 
-    fn execute(&self, args: &[Arg], outer_ctx: &EvalContext) -> Result<Expression> {
-        let num_args = args.len();
-
-        // first clone + add arguments using params and args
+    // lexical: args > ctx at definition > globals, the caller's ctx is never seen
+    fn execute(&self, args: &[Arg], _outer_ctx: &EvalContext) -> Result<Expression> {
         let eval_ctx = self.curry(args)?;
 
-        // then merge outer_ctx
-        // args > inner_ctx > outer_ctx
-
-        let eval_ctx = eval_ctx.merge_context(&outer_ctx);
-        let fn_node = self.body.get(0).unwrap(); // currently only on first part
-
-        // IMPORTANT:node is CLONED so the clone compares unequal because id changed
-        let cloned = fn_node.as_ref().clone();
+        // everything before the last expression runs first, last one is deferred for TCO
+        if self.body.len() > 1 {
+            let seq = Sequence {
+                ctx: eval_ctx,
+                body: Rc::clone(&self.body),
+                tail: true,
+                bind: true,
+            };
+            return Ok(SequenceExpr(seq));
+        }
 
         let res = DeferredExpression {
-            ctx: eval_ctx.clone(),
-            body: Rc::new(cloned),
+            ctx: eval_ctx,
+            body: Rc::clone(self.body.last().unwrap()),
         };
 
         let res = DeferredExpr(res);
@@ -223,6 +263,10 @@ impl Function for UserFunction {
         return Ok(res);
     }
 
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
     fn to_string(&self) -> String {
         self.to_string()
     }
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::message::*;
+
+// how much work one evaluation may do: None means no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalLimits {
+    pub max_steps: Option<u64>, // iterations of the evaluate_tco loop, over all nested calls
+    pub timeout: Option<Duration>, // wall clock time
+    pub max_depth: Option<usize>, // length of call_stack or fn_stack
+}
+
+impl EvalLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_steps.is_none() && self.timeout.is_none() && self.max_depth.is_none()
+    }
+}
+
+// limits plus what has been used so far
+struct Budget {
+    limits: EvalLimits,
+    steps: u64,
+    deadline: Option<Instant>,
+}
+
+// Instant::now is slow compared to a step, so only check the clock this often
+const CLOCK_INTERVAL: u64 = 1024;
+
+// nested evaluate_tco calls (lcons heads, lazy tails...) share one budget
+thread_local! {
+    static BUDGET: RefCell<Option<Budget>> = RefCell::new(None);
+}
+
+// starts a budget for one top level evaluation, ended when dropped
+// if one is already running (e.g import inside a file) that one keeps counting
+pub struct BudgetGuard {
+    started: bool,
+}
+
+impl BudgetGuard {
+    pub fn start(limits: EvalLimits) -> BudgetGuard {
+        let started = BUDGET.with(|budget| {
+            let mut budget = budget.borrow_mut();
+            if budget.is_some() || limits.is_unlimited() {
+                return false;
+            }
+
+            budget.replace(Budget {
+                limits,
+                steps: 0,
+                deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            });
+            true
+        });
+
+        BudgetGuard { started }
+    }
+}
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        if self.started {
+            BUDGET.with(|budget| budget.borrow_mut().take());
+        }
+    }
+}
+
+fn limit_error(limit: &str, msg: String) -> Ex {
+    Ex::new(&msg).with_kind(ErrorKind::Limit {
+        limit: limit.to_string(),
+    })
+}
+
+// called once per step of the evaluate_tco loop
+pub(crate) fn check_budget(call_len: usize, fn_len: usize) -> Result<()> {
+    BUDGET.with(|budget| {
+        let mut budget = budget.borrow_mut();
+        let budget = match budget.as_mut() {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+
+        budget.steps += 1;
+        let limits = &budget.limits;
+
+        if let Some(max_steps) = limits.max_steps {
+            if budget.steps > max_steps {
+                let msg = format!("Evaluation exceeded the limit of {} steps.", max_steps);
+                return Err(limit_error("steps", msg));
+            }
+        }
+
+        if let Some(max_depth) = limits.max_depth {
+            if call_len.max(fn_len) > max_depth {
+                let msg = format!("Evaluation exceeded the maximum depth of {}.", max_depth);
+                return Err(limit_error("depth", msg));
+            }
+        }
+
+        if let (Some(deadline), Some(timeout)) = (budget.deadline, limits.timeout) {
+            if budget.steps % CLOCK_INTERVAL == 0 && Instant::now() > deadline {
+                let msg = format!(
+                    "Evaluation exceeded the time limit of {}ms.",
+                    timeout.as_millis()
+                );
+                return Err(limit_error("time", msg));
+            }
+        }
+
+        Ok(())
+    })
+}
+
+// set from the Ctrl-C handler, which runs on another thread
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// forget a Ctrl-C that came in while nothing was running
+pub fn clear_interrupt() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+// called once per step of the evaluate_tco loop, like check_budget
+pub(crate) fn check_interrupt() -> Result<()> {
+    take_interrupt(&INTERRUPTED)
+}
+
+// error once per Ctrl-C
+fn take_interrupt(flag: &AtomicBool) -> Result<()> {
+    if flag.swap(false, Ordering::SeqCst) {
+        return Err(Ex::new("Interrupted").with_kind(ErrorKind::Interrupt));
+    }
+    Ok(())
+}
+
+// own flag: tests run in parallel and share INTERRUPTED
+#[test]
+fn limits_test_interrupt() {
+    let flag = AtomicBool::new(false);
+    assert!(take_interrupt(&flag).is_ok());
+
+    flag.store(true, Ordering::SeqCst);
+    let err = take_interrupt(&flag).unwrap_err();
+    assert_eq!(err.get_kind(), &ErrorKind::Interrupt);
+    assert_eq!(err.format_error(), "Error: Interrupted");
+
+    // only reported once
+    assert!(take_interrupt(&flag).is_ok());
+}
+
+#[test]
+fn limits_test_budget() {
+    let limits = EvalLimits {
+        max_steps: Some(3),
+        ..Default::default()
+    };
+
+    {
+        let _guard = BudgetGuard::start(limits);
+
+        // nested guard doesn't reset the count
+        let _inner = BudgetGuard::start(EvalLimits {
+            max_steps: Some(100),
+            ..Default::default()
+        });
+
+        assert!(check_budget(1, 1).is_ok());
+        assert!(check_budget(1, 1).is_ok());
+        assert!(check_budget(1, 1).is_ok());
+
+        let err = check_budget(1, 1).unwrap_err();
+        assert_eq!(
+            err.get_kind(),
+            &ErrorKind::Limit {
+                limit: String::from("steps")
+            }
+        );
+    }
+
+    // dropped: no budget any more
+    for _ in 0..10 {
+        assert!(check_budget(1, 1).is_ok());
+    }
+
+    let _guard = BudgetGuard::start(EvalLimits {
+        max_depth: Some(5),
+        ..Default::default()
+    });
+    assert!(check_budget(5, 2).is_ok());
+    assert!(check_budget(2, 6).is_err());
+}
@@ -0,0 +1,254 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use super::data_tco::*;
+use super::number::Number;
+
+// what a map value holds: a hash trie, keys compared with equals
+// set and del copy the path down to the key and share the rest, so maps stay cheap to copy
+#[derive(Clone, Default)]
+pub struct MapData {
+    root: Option<Rc<Node>>,
+    len: usize,
+    next_seq: u64, // entries remember when they were added, for insertion order
+}
+
+const BITS: u32 = 4; // of the hash used at each level
+const WIDTH: usize = 1 << BITS;
+
+#[derive(Clone)]
+struct Entry {
+    seq: u64,
+    pair: (DataValue, DataValue),
+}
+
+#[derive(Clone)]
+enum Node {
+    Leaf(u64, Vec<Entry>), // the keys with exactly this hash
+    Branch([Option<Rc<Node>>; WIDTH]),
+}
+
+fn slot(hash: u64, shift: u32) -> usize {
+    ((hash >> shift) & (WIDTH as u64 - 1)) as usize
+}
+
+impl MapData {
+    pub fn new() -> MapData {
+        MapData::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // in insertion order
+    pub fn iter(&self) -> std::vec::IntoIter<&(DataValue, DataValue)> {
+        let mut entries: Vec<&Entry> = vec![];
+        let mut nodes: Vec<&Node> = self.root.as_deref().into_iter().collect();
+
+        while let Some(node) = nodes.pop() {
+            match node {
+                Node::Leaf(_, leaf) => entries.extend(leaf.iter()),
+                Node::Branch(children) => {
+                    nodes.extend(children.iter().flatten().map(|c| c.as_ref()))
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.seq);
+        let pairs: Vec<&(DataValue, DataValue)> = entries.into_iter().map(|e| &e.pair).collect();
+        pairs.into_iter()
+    }
+
+    pub fn get(&self, key: &DataValue) -> Option<&DataValue> {
+        let hash = key_hash(key);
+        let mut node = self.root.as_deref()?;
+        let mut shift = 0;
+
+        loop {
+            match node {
+                Node::Leaf(leaf_hash, entries) if *leaf_hash == hash => {
+                    let entry = entries.iter().find(|e| e.pair.0.equals(key))?;
+                    return Some(&entry.pair.1);
+                }
+                Node::Leaf(..) => return None,
+                Node::Branch(children) => {
+                    node = children[slot(hash, shift)].as_deref()?;
+                    shift += BITS;
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &DataValue) -> bool {
+        self.get(key).is_some()
+    }
+
+    // replaces the value if the key is already there, otherwise adds it at the end
+    pub fn insert(&mut self, key: DataValue, value: DataValue) {
+        let entry = Entry {
+            seq: self.next_seq,
+            pair: (key, value),
+        };
+
+        let hash = key_hash(&entry.pair.0);
+        let (root, added) = insert_node(self.root.as_ref(), hash, 0, entry);
+        self.root = Some(root);
+
+        if added {
+            self.len += 1;
+            self.next_seq += 1;
+        }
+    }
+
+    // false if the key wasn't there
+    pub fn remove(&mut self, key: &DataValue) -> bool {
+        let Some(root) = &self.root else {
+            return false;
+        };
+
+        match remove_node(root, key_hash(key), 0, key) {
+            Some(root) => {
+                self.root = root;
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// a copy of the nodes on the way to the key, and whether the key is new
+fn insert_node(node: Option<&Rc<Node>>, hash: u64, shift: u32, entry: Entry) -> (Rc<Node>, bool) {
+    match node.map(|n| n.as_ref()) {
+        None => (Rc::new(Node::Leaf(hash, vec![entry])), true),
+        Some(Node::Leaf(leaf_hash, entries)) if *leaf_hash == hash => {
+            let mut entries = entries.clone();
+            let added = match entries.iter_mut().find(|e| e.pair.0.equals(&entry.pair.0)) {
+                Some(existing) => {
+                    existing.pair.1 = entry.pair.1;
+                    false
+                }
+                None => {
+                    entries.push(entry);
+                    true
+                }
+            };
+            (Rc::new(Node::Leaf(hash, entries)), added)
+        }
+        // a different hash: put the leaf in a branch and go on from there
+        // two hashes differ in some slot, so this stops before running out of bits
+        Some(Node::Leaf(leaf_hash, _)) => {
+            let mut children: [Option<Rc<Node>>; WIDTH] = Default::default();
+            children[slot(*leaf_hash, shift)] = node.cloned();
+            insert_node(Some(&Rc::new(Node::Branch(children))), hash, shift, entry)
+        }
+        Some(Node::Branch(children)) => {
+            let mut children = children.clone();
+            let i = slot(hash, shift);
+            let (child, added) = insert_node(children[i].as_ref(), hash, shift + BITS, entry);
+            children[i] = Some(child);
+            (Rc::new(Node::Branch(children)), added)
+        }
+    }
+}
+
+// None if the key isn't there, otherwise the node without it (None once it's empty)
+fn remove_node(node: &Node, hash: u64, shift: u32, key: &DataValue) -> Option<Option<Rc<Node>>> {
+    match node {
+        Node::Leaf(leaf_hash, entries) => {
+            if *leaf_hash != hash {
+                return None;
+            }
+
+            let i = entries.iter().position(|e| e.pair.0.equals(key))?;
+            let mut entries = entries.clone();
+            entries.remove(i);
+            Some((!entries.is_empty()).then(|| Rc::new(Node::Leaf(hash, entries))))
+        }
+        Node::Branch(children) => {
+            let i = slot(hash, shift);
+            let child = remove_node(children[i].as_deref()?, hash, shift + BITS, key)?;
+
+            let mut children = children.clone();
+            children[i] = child;
+            let empty = children.iter().all(Option::is_none);
+            Some((!empty).then(|| Rc::new(Node::Branch(children))))
+        }
+    }
+}
+
+// equal keys hash the same: numbers by value, so 1, 1.0 and 2/2 do
+fn key_hash(key: &DataValue) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_into(key, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_into(key: &DataValue, hasher: &mut DefaultHasher) {
+    match key {
+        Num(_) | BigNum(_) | Ratio(_) | Float(_) => {
+            let float = Number::from_value(key).map_or(0.0, |num| num.to_f64());
+            let float = if float == 0.0 { 0.0 } else { float }; // -0.0 equals 0.0
+            (0u8, float.to_bits()).hash(hasher);
+        }
+        Bool(b) => (1u8, b).hash(hasher),
+        Str(string) => (2u8, string).hash(hasher),
+        ListValue(lst) => {
+            (3u8, lst.len()).hash(hasher);
+            lst.iter().for_each(|value| hash_into(value, hasher));
+        }
+        // entries in any order, like map_equals
+        MapValue(map) => {
+            let entries = map.iter().fold(0u64, |acc, (key, value)| {
+                acc.wrapping_add(key_hash(key) ^ key_hash(value).rotate_left(1))
+            });
+            (4u8, map.len(), entries).hash(hasher);
+        }
+        _ => 5u8.hash(hasher), // not keys, only values in a map key: equals tells them apart
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(map: &MapData) -> Vec<String> {
+        map.iter().map(|(key, _)| key.to_string()).collect()
+    }
+
+    #[test]
+    fn map_data_test() {
+        let mut map = MapData::new();
+        for i in 0..1000 {
+            map.insert(Num(i), Num(i * i));
+        }
+        let copy = map.clone();
+
+        // same key by value: replaces and keeps its place
+        map.insert(Float(2.0), Str(String::from("two")));
+        map.insert(Str(String::from("a")), Bool(true));
+
+        assert_eq!(map.len(), 1001);
+        assert_eq!(map.get(&Num(2)).unwrap().to_string(), "two");
+        assert_eq!(map.get(&Num(999)).unwrap().to_string(), "998001");
+        assert!(map.get(&Num(1000)).is_none());
+        assert_eq!(keys(&map)[..3], ["0", "1", "2"]);
+        assert_eq!(keys(&map).last().unwrap(), "a");
+
+        assert!(map.remove(&Num(1)));
+        assert!(!map.remove(&Num(1)));
+        assert_eq!(map.len(), 1000);
+        assert_eq!(keys(&map)[..3], ["0", "2", "3"]);
+
+        // the copy shares nodes but doesn't see the changes
+        assert_eq!(copy.len(), 1000);
+        assert_eq!(copy.get(&Num(2)).unwrap().to_string(), "4");
+        assert!(copy.contains_key(&Num(1)));
+    }
+}
@@ -0,0 +1,408 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::constants::*;
+use crate::message::*;
+
+use super::bigint::BigInt;
+use super::builtins_tco::{get_nums, BuiltIn, BuiltInBuilder};
+use super::context_tco::EvalContext;
+use super::data_tco::*;
+use super::evaluator_tco::*;
+use super::function_tco::arity_error;
+use super::number::Number;
+use super::params::Params;
+
+// numeric builtins beyond the arithmetic in builtins_tco, registered in setup_context
+
+fn one_num(name: &str, args: &[Arg]) -> Result<Number> {
+    let nums = get_nums(args)?;
+    if nums.len() != 1 {
+        return Err(arity_error(name, 1, nums.len()));
+    }
+    Ok(nums[0].clone())
+}
+
+fn two_nums(name: &str, args: &[Arg]) -> Result<(Number, Number)> {
+    let nums = get_nums(args)?;
+    if nums.len() != 2 {
+        return Err(arity_error(name, 2, nums.len()));
+    }
+    Ok((nums[0].clone(), nums[1].clone()))
+}
+
+fn expect_integer(name: &str, num: &Number) -> Result<BigInt> {
+    num.to_integer().ok_or_else(|| {
+        let value = num.clone().into_value();
+        let msg = format!(
+            "'{}' expected an integer but got '{}'",
+            name,
+            value.to_string()
+        );
+        value.type_error("integer", &msg)
+    })
+}
+
+fn get_integers(name: &str, args: &[Arg]) -> Result<Vec<BigInt>> {
+    get_nums(args)?
+        .iter()
+        .map(|num| expect_integer(name, num))
+        .collect()
+}
+
+fn abs(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let num = one_num(ABS, args)?;
+    let zero = Number::Int(0);
+
+    let result = match num.compare(&zero) {
+        Some(Ordering::Less) => zero.sub(&num),
+        _ => num,
+    };
+    Ok(EvaluatedExpr(result.into_value()))
+}
+
+// the first number that no other beats: (min 1 1.0) => 1
+fn extremum(args: &[Arg], wins: Ordering) -> Result<Expression> {
+    get_nums(args)?
+        .into_iter()
+        .reduce(|best, num| match num.compare(&best) {
+            Some(ord) if ord == wins => num,
+            _ => best,
+        })
+        .map(|num| EvaluatedExpr(num.into_value()))
+        .ok_or(Ex::new("Could not compare provided expression"))
+}
+
+fn min(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    extremum(args, Ordering::Less)
+}
+
+fn max(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    extremum(args, Ordering::Greater)
+}
+
+// exact results are computed in one step, which limits can't interrupt: refuse bigger ones
+const MAX_RESULT_BITS: u64 = 1 << 20;
+
+fn too_big(name: &str, bits: u64) -> Result<()> {
+    if bits > MAX_RESULT_BITS {
+        return errf!(
+            "'{}' can't give a number of more than {} bits.",
+            name,
+            MAX_RESULT_BITS
+        );
+    }
+    Ok(())
+}
+
+// exact unless a float is involved: (pow 2 -2) => 1/4, (pow 2 0.5) => 1.4142135623730951
+fn pow(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let (base, exp) = two_nums(POW, args)?;
+
+    // 0, 1 and -1 stay that size whatever the exponent
+    if let (Some(bits), Number::Int(n)) = (base.bits(), &exp) {
+        if bits > 1 {
+            too_big(POW, bits.saturating_mul(n.unsigned_abs()))?;
+        }
+    }
+
+    match base.pow(&exp) {
+        // like sqrt: (pow -8 1/3) has no real result
+        Some(Number::Float(result))
+            if result.is_nan() && !base.to_f64().is_nan() && !exp.to_f64().is_nan() =>
+        {
+            let (base, exp) = (base.into_value(), exp.into_value());
+            errf!(
+                "'{}' is undefined for {} and {}.",
+                POW,
+                base.to_string(),
+                exp.to_string()
+            )
+        }
+        Some(result) => Ok(EvaluatedExpr(result.into_value())),
+        None => {
+            let msg = format!("'{}' can't raise 0 to a negative power.", POW);
+            Err(Ex::new(&msg).with_kind(ErrorKind::ZeroDivision))
+        }
+    }
+}
+
+// always a float: (sqrt 4) => 2.0. a NaN from a number that isn't NaN means it's out of the domain
+fn float_fn(name: &str, args: &[Arg], op: fn(f64) -> f64) -> Result<Expression> {
+    let num = one_num(name, args)?;
+    let result = op(num.to_f64());
+
+    if result.is_nan() && !num.to_f64().is_nan() {
+        return errf!(
+            "'{}' is undefined for {}.",
+            name,
+            num.into_value().to_string()
+        );
+    }
+    Ok(EvaluatedExpr(Float(result)))
+}
+
+fn sqrt(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(SQRT, args, f64::sqrt)
+}
+
+fn sin(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(SIN, args, f64::sin)
+}
+
+fn cos(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(COS, args, f64::cos)
+}
+
+fn tan(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(TAN, args, f64::tan)
+}
+
+fn asin(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(ASIN, args, f64::asin)
+}
+
+fn acos(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(ACOS, args, f64::acos)
+}
+
+fn atan(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(ATAN, args, f64::atan)
+}
+
+fn exp(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(EXP, args, f64::exp)
+}
+
+// natural log, (log 0) => -inf
+fn log(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(LOG, args, f64::ln)
+}
+
+fn log10(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    float_fn(LOG10, args, f64::log10)
+}
+
+// never negative, (gcd 0 0) => 0
+fn gcd(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let result = get_integers(GCD, args)?
+        .iter()
+        .fold(BigInt::zero(), |acc, int| acc.gcd(int));
+    Ok(EvaluatedExpr(Number::Big(Rc::new(result)).into_value()))
+}
+
+// never negative, 0 if any of them is 0
+fn lcm(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let result = get_integers(LCM, args)?
+        .iter()
+        .fold(BigInt::from_i64(1), |acc, int| {
+            if acc.is_zero() || int.is_zero() {
+                return BigInt::zero();
+            }
+            acc.mul(int).abs().div_rem(&acc.gcd(int)).unwrap().0
+        });
+    Ok(EvaluatedExpr(Number::Big(Rc::new(result)).into_value()))
+}
+
+// -1, 0 or 1
+fn sign(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let num = one_num(SIGN, args)?;
+
+    match num.compare(&Number::Int(0)) {
+        Some(ord) => Ok(EvaluatedExpr(Num(ord as NumType))),
+        None => Ok(EvaluatedExpr(num.into_value())), // NaN
+    }
+}
+
+fn parity(name: &str, args: &[Arg], even: bool) -> Result<Expression> {
+    let int = expect_integer(name, &one_num(name, args)?)?;
+    let (_, rem) = int.div_rem(&BigInt::from_i64(2)).unwrap();
+    Ok(EvaluatedExpr(Bool(rem.is_zero() == even)))
+}
+
+fn is_even(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    parity(EVEN, args, true)
+}
+
+fn is_odd(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    parity(ODD, args, false)
+}
+
+// on the 64 bit two's complement representation
+// on integers of any size, negative ones in two's complement: (band -1 x) => x
+fn bitwise(name: &str, args: &[Arg], op: fn(u32, u32) -> u32) -> Result<Expression> {
+    get_integers(name, args)?
+        .into_iter()
+        .reduce(|left, right| left.bitwise(&right, op))
+        .map(|num| EvaluatedExpr(Number::Big(Rc::new(num)).into_value()))
+        .ok_or(Ex::new("Could not combine provided expression"))
+}
+
+fn band(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    bitwise(BAND, args, |left, right| left & right)
+}
+
+fn bor(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    bitwise(BOR, args, |left, right| left | right)
+}
+
+fn bxor(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    bitwise(BXOR, args, |left, right| left ^ right)
+}
+
+// shifts are multiplying or flooring division by 2^amount, so they never overflow
+fn shift_args(name: &str, args: &[Arg]) -> Result<(BigInt, u64)> {
+    let (num, amount) = two_nums(name, args)?;
+    let num = expect_integer(name, &num)?;
+
+    match expect_integer(name, &amount)?.to_i64() {
+        Some(amount) if amount >= 0 => Ok((num, amount as u64)),
+        _ => errf!(
+            "'{}' can't shift by {}.",
+            name,
+            amount.into_value().to_string()
+        ),
+    }
+}
+
+fn shl(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let (num, amount) = shift_args(SHL, args)?;
+    if !num.is_zero() {
+        too_big(SHL, num.bits().saturating_add(amount))?;
+    }
+    Ok(EvaluatedExpr(
+        Number::Big(Rc::new(num.shl(amount))).into_value(),
+    ))
+}
+
+// rounds down like an arithmetic shift: (shr -5 1) => -3
+fn shr(args: &[Arg], _context: &EvalContext) -> Result<Expression> {
+    let (num, amount) = shift_args(SHR, args)?;
+    Ok(EvaluatedExpr(
+        Number::Big(Rc::new(num.shr(amount))).into_value(),
+    ))
+}
+
+fn build_unary(name: &str, exec: fn(&[Arg], &EvalContext) -> Result<Expression>) -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(name)
+        .params(Params::new_finite(vec!["number"]))
+        .exec(exec)
+        .build()
+}
+
+fn build_variadic(name: &str, exec: fn(&[Arg], &EvalContext) -> Result<Expression>) -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(name)
+        .params(Params::new_infinite(2))
+        .exec(exec)
+        .build()
+}
+
+pub fn build_pow() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(POW)
+        .params(Params::new_finite(vec!["base", "exponent"]))
+        .exec(pow)
+        .build()
+}
+
+pub fn build_shl() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(SHL)
+        .params(Params::new_finite(vec!["number", "amount"]))
+        .exec(shl)
+        .build()
+}
+
+pub fn build_shr() -> BuiltIn {
+    BuiltInBuilder::new_default()
+        .name(SHR)
+        .params(Params::new_finite(vec!["number", "amount"]))
+        .exec(shr)
+        .build()
+}
+
+pub fn build_abs() -> BuiltIn {
+    build_unary(ABS, abs)
+}
+
+pub fn build_min() -> BuiltIn {
+    build_variadic(MIN, min)
+}
+
+pub fn build_max() -> BuiltIn {
+    build_variadic(MAX, max)
+}
+
+pub fn build_sqrt() -> BuiltIn {
+    build_unary(SQRT, sqrt)
+}
+
+pub fn build_gcd() -> BuiltIn {
+    build_variadic(GCD, gcd)
+}
+
+pub fn build_lcm() -> BuiltIn {
+    build_variadic(LCM, lcm)
+}
+
+pub fn build_sign() -> BuiltIn {
+    build_unary(SIGN, sign)
+}
+
+pub fn build_even() -> BuiltIn {
+    build_unary(EVEN, is_even)
+}
+
+pub fn build_odd() -> BuiltIn {
+    build_unary(ODD, is_odd)
+}
+
+pub fn build_sin() -> BuiltIn {
+    build_unary(SIN, sin)
+}
+
+pub fn build_cos() -> BuiltIn {
+    build_unary(COS, cos)
+}
+
+pub fn build_tan() -> BuiltIn {
+    build_unary(TAN, tan)
+}
+
+pub fn build_asin() -> BuiltIn {
+    build_unary(ASIN, asin)
+}
+
+pub fn build_acos() -> BuiltIn {
+    build_unary(ACOS, acos)
+}
+
+pub fn build_atan() -> BuiltIn {
+    build_unary(ATAN, atan)
+}
+
+pub fn build_exp() -> BuiltIn {
+    build_unary(EXP, exp)
+}
+
+pub fn build_log() -> BuiltIn {
+    build_unary(LOG, log)
+}
+
+pub fn build_log10() -> BuiltIn {
+    build_unary(LOG10, log10)
+}
+
+pub fn build_band() -> BuiltIn {
+    build_variadic(BAND, band)
+}
+
+pub fn build_bor() -> BuiltIn {
+    build_variadic(BOR, bor)
+}
+
+pub fn build_bxor() -> BuiltIn {
+    build_variadic(BXOR, bxor)
+}
@@ -1,7 +1,14 @@
+pub mod adt;
+pub mod bigint;
 pub mod builtins_tco;
 pub mod context_tco;
 pub mod data_tco;
 pub mod eval_helpers_tco;
 pub mod evaluator_tco;
 pub mod function_tco;
+pub mod limits;
+pub mod map;
+pub mod math;
+pub mod number;
 pub mod params;
+pub mod rational;
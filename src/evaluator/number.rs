@@ -0,0 +1,405 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use crate::constants::NumType;
+use crate::message::*;
+
+use super::bigint::BigInt;
+use super::data_tco::*;
+use super::rational::Rational;
+
+// what numeric builtins compute with: NumType while it fits, BigInt once it overflows
+// mixing in a Ratio gives a Ratio, mixing in a Float gives a Float
+#[derive(Clone)]
+pub enum Number {
+    Int(NumType),
+    Big(Rc<BigInt>),
+    Ratio(Rc<Rational>), // never a whole number, into_value demotes those
+    Float(f64),
+}
+
+// decimal literal: needs a . or an exponent, otherwise it's an integer (or a symbol like inf)
+pub fn parse_float(token: &str) -> Option<f64> {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    let starts_numeric = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+    let has_digit = digits.chars().any(|c| c.is_ascii_digit());
+    let is_decimal = digits.contains(['.', 'e', 'E']);
+    let valid_chars = digits
+        .chars()
+        .all(|c| c.is_ascii_digit() || ".eE+-".contains(c));
+
+    if !(starts_numeric && has_digit && is_decimal && valid_chars) {
+        return None;
+    }
+    token.parse::<f64>().ok()
+}
+
+// 3.0 stays 3.0 so it reads back as a float
+pub fn format_float(float: f64) -> String {
+    format!("{:?}", float)
+}
+
+impl Number {
+    // bools count as 0/1, like expect_num
+    pub fn from_value(value: &DataValue) -> Result<Number> {
+        match value {
+            BigNum(big) => Ok(Number::Big(Rc::clone(big))),
+            Ratio(ratio) => Ok(Number::Ratio(Rc::clone(ratio))),
+            Float(float) => Ok(Number::Float(*float)),
+            _ => value.expect_num().map(Number::Int),
+        }
+    }
+
+    // back to Num if it fits again, whole ratios become integers
+    pub fn into_value(self) -> DataValue {
+        match self {
+            Number::Int(n) => Num(n),
+            Number::Big(big) => match big.to_i64() {
+                Some(n) => Num(n),
+                None => BigNum(big),
+            },
+            Number::Ratio(ratio) if ratio.is_integer() => {
+                Number::Big(Rc::new(ratio.numer().clone())).into_value()
+            }
+            Number::Ratio(ratio) => Ratio(ratio),
+            Number::Float(float) => Float(float),
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self, Number::Float(_))
+    }
+
+    fn is_ratio(&self) -> bool {
+        matches!(self, Number::Ratio(_))
+    }
+
+    // size of an exact number: the bigger of numerator and denominator for a ratio, None for floats
+    pub fn bits(&self) -> Option<u64> {
+        match self {
+            Number::Int(n) => Some((64 - n.unsigned_abs().leading_zeros()) as u64),
+            Number::Big(big) => Some(big.bits()),
+            Number::Ratio(ratio) => Some(ratio.numer().bits().max(ratio.denom().bits())),
+            Number::Float(_) => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(n) => *n as f64,
+            Number::Big(big) => big.to_f64(),
+            Number::Ratio(ratio) => ratio.to_f64(),
+            Number::Float(float) => *float,
+        }
+    }
+
+    // None for ratios and floats, even whole ones
+    pub fn to_integer(&self) -> Option<BigInt> {
+        match self {
+            Number::Int(_) | Number::Big(_) => Some(self.to_big()),
+            Number::Ratio(_) | Number::Float(_) => None,
+        }
+    }
+
+    // callers deal with floats first
+    fn to_ratio(&self) -> Rational {
+        match self {
+            Number::Ratio(ratio) => ratio.as_ref().clone(),
+            _ => Rational::from_int(self.to_big()),
+        }
+    }
+
+    // callers deal with floats and ratios first
+    fn to_big(&self) -> BigInt {
+        match self {
+            Number::Int(n) => BigInt::from_i64(*n),
+            Number::Big(big) => big.as_ref().clone(),
+            Number::Ratio(_) | Number::Float(_) => unreachable!("fraction passed to to_big"),
+        }
+    }
+
+    // try the i64 operation first, redo it with BigInt if that overflows
+    fn checked(
+        &self,
+        other: &Number,
+        small: fn(NumType, NumType) -> Option<NumType>,
+        big: fn(&BigInt, &BigInt) -> BigInt,
+        ratio: fn(&Rational, &Rational) -> Rational,
+        float: fn(f64, f64) -> f64,
+    ) -> Number {
+        if self.is_float() || other.is_float() {
+            return Number::Float(float(self.to_f64(), other.to_f64()));
+        }
+
+        if self.is_ratio() || other.is_ratio() {
+            return Number::Ratio(Rc::new(ratio(&self.to_ratio(), &other.to_ratio())));
+        }
+
+        if let (Number::Int(left), Number::Int(right)) = (self, other) {
+            if let Some(result) = small(*left, *right) {
+                return Number::Int(result);
+            }
+        }
+
+        Number::Big(Rc::new(big(&self.to_big(), &other.to_big())))
+    }
+
+    pub fn add(&self, other: &Number) -> Number {
+        self.checked(
+            other,
+            NumType::checked_add,
+            BigInt::add,
+            Rational::add,
+            |a, b| a + b,
+        )
+    }
+
+    pub fn sub(&self, other: &Number) -> Number {
+        self.checked(
+            other,
+            NumType::checked_sub,
+            BigInt::sub,
+            Rational::sub,
+            |a, b| a - b,
+        )
+    }
+
+    pub fn mul(&self, other: &Number) -> Number {
+        self.checked(
+            other,
+            NumType::checked_mul,
+            BigInt::mul,
+            Rational::mul,
+            |a, b| a * b,
+        )
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(n) => *n == 0,
+            Number::Big(big) => big.is_zero(),
+            Number::Ratio(ratio) => ratio.numer().is_zero(),
+            Number::Float(float) => *float == 0.0,
+        }
+    }
+
+    // exact: integers that don't divide evenly give a Ratio. None when dividing by zero
+    pub fn div(&self, other: &Number) -> Option<Number> {
+        if self.is_float() || other.is_float() {
+            return (!other.is_zero()).then(|| Number::Float(self.to_f64() / other.to_f64()));
+        }
+
+        let quot = self.to_ratio().div(&other.to_ratio())?;
+        Some(Number::Ratio(Rc::new(quot)))
+    }
+
+    // rounds down, remainder has the sign of the divisor. None when dividing by zero
+    pub fn div_mod_floor(&self, other: &Number) -> Option<(Number, Number)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        if self.is_float() || other.is_float() {
+            let (left, right) = (self.to_f64(), other.to_f64());
+            let mut rem = left % right;
+            if rem != 0.0 && (rem < 0.0) != (right < 0.0) {
+                rem += right;
+            }
+            return Some((Number::Float((left / right).floor()), Number::Float(rem)));
+        }
+
+        if self.is_ratio() || other.is_ratio() {
+            let (left, right) = (self.to_ratio(), other.to_ratio());
+            let quot = Rational::from_int(left.div(&right)?.floor());
+            let rem = left.sub(&quot.mul(&right));
+            return Some((Number::Ratio(Rc::new(quot)), Number::Ratio(Rc::new(rem))));
+        }
+
+        if let (Number::Int(left), Number::Int(right)) = (self, other) {
+            // only i64::MIN / -1 overflows
+            if let (Some(quot), Some(rem)) = (left.checked_div(*right), left.checked_rem(*right)) {
+                if rem != 0 && (rem < 0) != (*right < 0) {
+                    return Some((Number::Int(quot - 1), Number::Int(rem + right)));
+                }
+                return Some((Number::Int(quot), Number::Int(rem)));
+            }
+        }
+
+        let (quot, rem) = self.to_big().div_mod_floor(&other.to_big())?;
+        Some((Number::Big(Rc::new(quot)), Number::Big(Rc::new(rem))))
+    }
+
+    // exact for an integer exponent unless the base is a float. None for 0 to a negative power
+    pub fn pow(&self, exp: &Number) -> Option<Number> {
+        match exp {
+            Number::Int(exp) if !self.is_float() => {
+                let mut result = Number::Int(1);
+                let mut square = self.clone();
+                let mut remaining = exp.unsigned_abs();
+
+                while remaining > 0 {
+                    if remaining & 1 == 1 {
+                        result = result.mul(&square);
+                    }
+                    remaining >>= 1;
+                    if remaining > 0 {
+                        square = square.mul(&square);
+                    }
+                }
+
+                if *exp < 0 {
+                    return Number::Int(1).div(&result);
+                }
+                Some(result)
+            }
+            _ => Some(Number::Float(self.to_f64().powf(exp.to_f64()))),
+        }
+    }
+
+    // None when a NaN is involved
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(left), Number::Int(right)) => Some(left.cmp(right)),
+            (Number::Float(left), Number::Float(right)) => left.partial_cmp(right),
+            (Number::Float(left), _) => float_cmp(*left, other),
+            (_, Number::Float(right)) => float_cmp(*right, self).map(Ordering::reverse),
+            _ if self.is_ratio() || other.is_ratio() => {
+                Some(self.to_ratio().cmp(&other.to_ratio()))
+            }
+            _ => Some(self.to_big().cmp(&other.to_big())),
+        }
+    }
+
+    // integers are left alone, fractions are rounded by float_fn or ratio_fn. None for inf and NaN
+    pub fn to_int(
+        &self,
+        float_fn: fn(f64) -> f64,
+        ratio_fn: fn(&Rational) -> BigInt,
+    ) -> Option<Number> {
+        match self {
+            Number::Float(float) => {
+                BigInt::from_f64(float_fn(*float)).map(|big| Number::Big(Rc::new(big)))
+            }
+            Number::Ratio(ratio) => Some(Number::Big(Rc::new(ratio_fn(ratio)))),
+            _ => Some(self.clone()),
+        }
+    }
+}
+
+// exactly, with the float as the fraction it stands for, so eq stays transitive:
+// 2^60 + 1 isn't equal to 2^60 as a float, which is equal to 2^60
+fn float_cmp(float: f64, exact: &Number) -> Option<Ordering> {
+    match Rational::from_f64(float) {
+        Some(ratio) => Some(ratio.cmp(&exact.to_ratio())),
+        None if float.is_nan() => None,
+        None if float > 0.0 => Some(Ordering::Greater),
+        None => Some(Ordering::Less),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_test() {
+        let max = Number::Int(NumType::MAX);
+        let one = Number::Int(1);
+
+        let sum = max.add(&one);
+        assert_eq!(sum.clone().into_value().to_string(), "9223372036854775808");
+
+        // comes back down to Num
+        assert!(matches!(sum.sub(&one).into_value(), Num(NumType::MAX)));
+
+        let min = Number::Int(NumType::MIN);
+        let (quot, rem) = min.div_mod_floor(&Number::Int(-1)).unwrap();
+        assert_eq!(quot.into_value().to_string(), "9223372036854775808");
+        assert!(rem.is_zero());
+
+        assert!(one.div_mod_floor(&Number::Int(0)).is_none());
+        assert_eq!(sum.compare(&max), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn float_test() {
+        let half = Number::Float(0.5);
+        let two = Number::Int(2);
+
+        assert_eq!(half.add(&two).into_value().to_string(), "2.5");
+        assert_eq!(two.div(&Number::Float(4.0)).unwrap().to_f64(), 0.5);
+        assert!(half.div(&Number::Int(0)).is_none());
+
+        let (quot, rem) = Number::Float(-7.5).div_mod_floor(&two).unwrap();
+        assert_eq!((quot.to_f64(), rem.to_f64()), (-4.0, 0.5));
+
+        assert_eq!(half.compare(&two), Some(Ordering::Less));
+        assert_eq!(Number::Float(f64::NAN).compare(&two), None);
+
+        let big = Number::Float(1e20)
+            .to_int(f64::trunc, Rational::trunc)
+            .unwrap();
+        assert_eq!(big.into_value().to_string(), "100000000000000000000");
+        assert!(Number::Float(f64::INFINITY)
+            .to_int(f64::floor, Rational::floor)
+            .is_none());
+
+        assert_eq!(parse_float("1e-3"), Some(0.001));
+        assert_eq!(parse_float("-.5"), Some(-0.5));
+        assert_eq!(parse_float("12"), None);
+        assert_eq!(parse_float("inf"), None);
+        assert_eq!(parse_float("e5"), None);
+        assert_eq!(format_float(3.0), "3.0");
+    }
+
+    #[test]
+    fn ratio_test() {
+        let seven = Number::Int(7);
+        let two = Number::Int(2);
+
+        let half = Number::Int(1).div(&two).unwrap();
+        assert_eq!(half.clone().into_value().to_string(), "1/2");
+        assert_eq!(seven.div(&two).unwrap().into_value().to_string(), "7/2");
+        // whole results come back as integers
+        assert!(matches!(half.mul(&two).into_value(), Num(1)));
+        assert!(matches!(
+            Number::Int(6).div(&two).unwrap().into_value(),
+            Num(3)
+        ));
+        assert!(seven.div(&Number::Int(0)).is_none());
+
+        assert_eq!(
+            half.add(&Number::Float(0.25)).into_value().to_string(),
+            "0.75"
+        );
+        assert_eq!(half.compare(&Number::Float(0.5)), Some(Ordering::Equal));
+        assert_eq!(half.compare(&Number::Int(1)), Some(Ordering::Less));
+
+        let (quot, rem) = seven.div_mod_floor(&half).unwrap();
+        assert!(matches!(quot.into_value(), Num(14)));
+        assert!(rem.is_zero());
+
+        let third = Number::Int(-1).div(&Number::Int(3)).unwrap();
+        let floored = third.to_int(f64::floor, Rational::floor).unwrap();
+        assert!(matches!(floored.into_value(), Num(-1)));
+        assert!(third.to_integer().is_none());
+
+        assert_eq!(
+            two.pow(&Number::Int(64)).unwrap().into_value().to_string(),
+            "18446744073709551616"
+        );
+        assert_eq!(
+            two.pow(&Number::Int(-2)).unwrap().into_value().to_string(),
+            "1/4"
+        );
+        assert_eq!(
+            third.pow(&Number::Int(3)).unwrap().into_value().to_string(),
+            "-1/27"
+        );
+        assert_eq!(
+            Number::Float(2.0).pow(&Number::Int(3)).unwrap().to_f64(),
+            8.0
+        );
+        assert!(Number::Int(0).pow(&Number::Int(-1)).is_none());
+    }
+}
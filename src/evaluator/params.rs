@@ -25,9 +25,6 @@ impl FiniteParams {
         let mut new_params = self.received_args.clone();
         new_params.extend_from_slice(args);
 
-        let str=new_params.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",");
-        // println!("apply_finite:{}", str);
-
         FiniteParams {
             params: self.params.clone(),
             params_idx: self.params_idx + args.len(),
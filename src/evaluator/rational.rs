@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use super::bigint::BigInt;
+
+// exact fraction, always in lowest terms with a positive denominator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    // None when the denominator is zero
+    pub fn new(numer: BigInt, denom: BigInt) -> Option<Rational> {
+        if denom.is_zero() {
+            return None;
+        }
+
+        let gcd = numer.gcd(&denom);
+        let (mut numer, _) = numer.div_rem(&gcd).unwrap();
+        let (mut denom, _) = denom.div_rem(&gcd).unwrap();
+
+        if denom.is_negative() {
+            numer = numer.neg();
+            denom = denom.neg();
+        }
+
+        Some(Rational { numer, denom })
+    }
+
+    pub fn from_int(int: BigInt) -> Rational {
+        Rational {
+            numer: int,
+            denom: BigInt::from_i64(1),
+        }
+    }
+
+    // exact: a finite float is a whole number times a power of 2. None for inf and NaN
+    pub fn from_f64(float: f64) -> Option<Rational> {
+        if !float.is_finite() {
+            return None;
+        }
+
+        let bits = float.to_bits();
+        let exp = ((bits >> 52) & 0x7ff) as i64;
+        let frac = bits & ((1 << 52) - 1);
+        // subnormals have no implicit leading bit
+        let (mantissa, exp) = match exp {
+            0 => (frac, -1074),
+            _ => (frac | 1 << 52, exp - 1075),
+        };
+
+        let mut numer = BigInt::from_i64(mantissa as i64);
+        if bits >> 63 == 1 {
+            numer = numer.neg();
+        }
+
+        match exp {
+            0.. => Some(Rational::from_int(numer.shl(exp as u64))),
+            _ => Rational::new(numer, BigInt::from_i64(1).shl(exp.unsigned_abs())),
+        }
+    }
+
+    pub fn numer(&self) -> &BigInt {
+        &self.numer
+    }
+
+    pub fn denom(&self) -> &BigInt {
+        &self.denom
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denom.to_i64() == Some(1)
+    }
+
+    pub fn add(&self, other: &Rational) -> Rational {
+        let numer = self
+            .numer
+            .mul(&other.denom)
+            .add(&other.numer.mul(&self.denom));
+        Rational::new(numer, self.denom.mul(&other.denom)).unwrap()
+    }
+
+    pub fn sub(&self, other: &Rational) -> Rational {
+        let numer = self
+            .numer
+            .mul(&other.denom)
+            .sub(&other.numer.mul(&self.denom));
+        Rational::new(numer, self.denom.mul(&other.denom)).unwrap()
+    }
+
+    pub fn mul(&self, other: &Rational) -> Rational {
+        Rational::new(self.numer.mul(&other.numer), self.denom.mul(&other.denom)).unwrap()
+    }
+
+    // None when dividing by zero
+    pub fn div(&self, other: &Rational) -> Option<Rational> {
+        Rational::new(self.numer.mul(&other.denom), self.denom.mul(&other.numer))
+    }
+
+    pub fn floor(&self) -> BigInt {
+        self.numer.div_mod_floor(&self.denom).unwrap().0
+    }
+
+    pub fn ceil(&self) -> BigInt {
+        let negated = Rational {
+            numer: self.numer.neg(),
+            denom: self.denom.clone(),
+        };
+        negated.floor().neg()
+    }
+
+    pub fn trunc(&self) -> BigInt {
+        self.numer.div_rem(&self.denom).unwrap().0
+    }
+
+    // halves round away from zero, like f64::round
+    pub fn round(&self) -> BigInt {
+        let half = Rational::new(BigInt::from_i64(1), BigInt::from_i64(2)).unwrap();
+        if self.numer.is_negative() {
+            self.sub(&half).ceil()
+        } else {
+            self.add(&half).floor()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numer.to_f64() / self.denom.to_f64()
+    }
+
+    // numerator/denominator, like 1/3 or -2/4. None if it isn't one or the denominator is 0
+    pub fn parse(string: &str) -> Option<Rational> {
+        let (numer, denom) = string.split_once('/')?;
+        if denom.starts_with('-') {
+            return None;
+        }
+
+        Rational::new(BigInt::parse(numer)?, BigInt::parse(denom)?)
+    }
+}
+
+impl Ord for Rational {
+    // denominators are positive so cross multiplying keeps the order
+    fn cmp(&self, other: &Rational) -> Ordering {
+        self.numer
+            .mul(&other.denom)
+            .cmp(&other.numer.mul(&self.denom))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            return write!(f, "{}", self.numer);
+        }
+        write!(f, "{}/{}", self.numer, self.denom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(string: &str) -> Rational {
+        Rational::parse(string).unwrap()
+    }
+
+    #[test]
+    fn rational_test() {
+        assert_eq!(ratio("2/4").to_string(), "1/2");
+        assert_eq!(ratio("-6/3").to_string(), "-2");
+        assert!(ratio("4/2").is_integer());
+        assert!(Rational::parse("1/0").is_none());
+        assert!(Rational::parse("1/-2").is_none());
+        assert!(Rational::parse("12").is_none());
+
+        let third = ratio("1/3");
+        let half = ratio("1/2");
+        assert_eq!(third.add(&half).to_string(), "5/6");
+        assert_eq!(third.sub(&half).to_string(), "-1/6");
+        assert_eq!(third.mul(&half).to_string(), "1/6");
+        assert_eq!(third.div(&half).unwrap().to_string(), "2/3");
+        assert!(third.div(&ratio("0/1")).is_none());
+        assert!(third < half);
+
+        let neg = ratio("-7/2");
+        assert_eq!(neg.floor().to_string(), "-4");
+        assert_eq!(neg.ceil().to_string(), "-3");
+        assert_eq!(neg.trunc().to_string(), "-3");
+        assert_eq!(neg.round().to_string(), "-4");
+        assert_eq!(ratio("5/3").round().to_string(), "2");
+        assert_eq!(half.to_f64(), 0.5);
+
+        let exact = |float: f64| Rational::from_f64(float).unwrap().to_string();
+        assert_eq!(exact(-0.375), "-3/8");
+        assert_eq!(exact(1e20), "100000000000000000000");
+        assert_eq!(exact(0.1), "3602879701896397/36028797018963968");
+        let smallest = BigInt::from_i64(1).shl(1074);
+        assert_eq!(exact(f64::from_bits(1)), format!("1/{}", smallest));
+        assert!(Rational::from_f64(f64::INFINITY).is_none());
+    }
+}
@@ -3,9 +3,11 @@ use regex::{Regex, RegexSet};
 
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::rc::Rc;
 
 use crate::constants::*;
 use crate::message::*;
+use crate::utils::span::{Source, Span};
 
 #[macro_export]
 macro_rules! lex {
@@ -19,6 +21,7 @@ pub use lex;
 #[derive(Debug)]
 pub struct Lexer {
     tokens: Vec<String>,
+    spans: Vec<Span>, // spans[i] is where tokens[i] is in the source
     pub idx: usize,
     original: String,
     stack: Vec<String>,
@@ -39,33 +42,249 @@ pub(crate) fn split_input(input: &str) -> Vec<String> {
     split
 }
 
-impl Lexer {
-    pub fn new(input: String) -> Result<Lexer> {
-        let original = input.clone();
-        let mut filtered = input;
+// true if token is a string literal: kept with quotes and escapes as written
+pub fn is_string_token(token: &str) -> bool {
+    token.starts_with(QUOTE)
+}
+
+// "a\tb" => a<tab>b
+pub fn unescape_string(token: &str) -> Result<String> {
+    let inner = &token[QUOTE.len()..token.len() - QUOTE.len()];
+    let mut result = String::new();
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != ESCAPE {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some('"') => result.push('"'),
+            Some('\\') => result.push('\\'),
+            Some(other) => return errf!("Unknown escape sequence '\\{}' in string.", other),
+            None => return err!("String can't end with an escape character."),
+        }
+    }
+
+    Ok(result)
+}
+
+// inverse of unescape_string
+pub fn escape_string(string: &str) -> String {
+    let mut result = String::from(QUOTE);
+
+    for c in string.chars() {
+        match c {
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            _ => result.push(c),
+        }
+    }
+
+    result.push_str(QUOTE);
+    result
+}
+
+// walks the input char by char so every token knows where it came from
+struct Scanner {
+    chars: Vec<char>,
+    idx: usize,
+    line: usize,
+    column: usize,
+    source: Rc<Source>,
+    tokens: Vec<String>,
+    spans: Vec<Span>,
+    current: String, // symbol being built
+    current_span: Option<Span>,
+}
+
+impl Scanner {
+    fn new(source: Rc<Source>) -> Scanner {
+        Scanner {
+            chars: source.text.chars().collect(),
+            idx: 0,
+            line: 1,
+            column: 1,
+            source,
+            tokens: vec![],
+            spans: vec![],
+            current: String::new(),
+            current_span: None,
+        }
+    }
 
-        if filtered.len() == 0 {
-            return err!("Can't parse an empty string");
+    fn span(&self, length: usize) -> Span {
+        Span::new(&self.source, self.line, self.column, length)
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = *self.chars.get(self.idx)?;
+        self.idx += 1;
+
+        if c.to_string() == NEWLINE {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
 
-        for token in SPLIT_TOKENS {
-            if DONT_ADD.contains(&token) {
-                filtered = filtered.replace(token, SPACE);
-            } else {
-                let sp = SPACE;
-                let replacement = format!("{}{}{}", sp, token, sp);
-                filtered = filtered.replace(token, &replacement);
+        Some(c)
+    }
+
+    fn push(&mut self, token: String, span: Span) {
+        self.tokens.push(token);
+        self.spans.push(span);
+    }
+
+    // end the symbol being built
+    fn flush(&mut self) {
+        if self.current.is_empty() {
+            return;
+        }
+
+        let mut span = self.current_span.take().unwrap();
+        span.length = self.current.chars().count();
+
+        let token = std::mem::take(&mut self.current);
+        self.push(token, span);
+    }
+
+    fn starts_with(&self, token: &str) -> bool {
+        let mut rest = self.chars[self.idx..].iter();
+        token.chars().all(|c| rest.next() == Some(&c))
+    }
+
+    // "..." is one token, kept with quotes and escapes as written
+    fn string_literal(&mut self) -> Result<()> {
+        let mut span = self.span(1);
+        let mut literal = String::new();
+        literal.push(self.advance().unwrap());
+
+        loop {
+            match self.advance() {
+                Some(c) if c == ESCAPE => {
+                    literal.push(c);
+                    if let Some(escaped) = self.advance() {
+                        literal.push(escaped);
+                    }
+                }
+                Some(c) => {
+                    literal.push(c);
+                    if c.to_string() == QUOTE {
+                        break;
+                    }
+                }
+                None => {
+                    let msg = format!("Unterminated string literal: {}", literal);
+                    return Err(Ex::new(&msg).with_span(Some(span)));
+                }
+            }
+        }
+
+        span.length = literal.chars().count();
+        self.push(literal, span);
+        Ok(())
+    }
+
+    // separate: add ';' whenever a top level expression closes, for files
+    fn scan(mut self, separate: bool) -> Result<(Vec<String>, Vec<Span>)> {
+        let mut depth = 0;
+        let mut in_comment = false;
+
+        while let Some(&c) = self.chars.get(self.idx) {
+            let char_string = c.to_string();
+
+            // #comment#: can't be nested
+            if char_string == COMMENT || in_comment {
+                self.flush();
+                in_comment = in_comment != (char_string == COMMENT);
+                self.advance();
+                continue;
+            }
+
+            if char_string == QUOTE {
+                self.flush();
+                self.string_literal()?;
+                continue;
+            }
+
+            let split_token = SPLIT_TOKENS.iter().find(|token| self.starts_with(token));
+
+            if let Some(&token) = split_token {
+                self.flush();
+                let length = token.chars().count();
+                let span = self.span(length);
+
+                if !DONT_ADD.contains(&token) {
+                    self.push(token.to_string(), span.clone());
+                }
+
+                // lists and maps count too: a ';' can't go inside a literal
+                if OPEN_TOKENS.contains(&token) {
+                    depth += 1;
+                    if depth > MAX_NESTING {
+                        let msg = format!("Can't nest more than {} brackets.", MAX_NESTING);
+                        return Err(Ex::new(&msg).with_span(Some(span)));
+                    }
+                } else if CLOSE_TOKENS.contains(&token) {
+                    if depth == 0 {
+                        let msg = format!("Found '{}' without an opening bracket.", token);
+                        return Err(Ex::new(&msg).with_span(Some(span)));
+                    }
+                    depth -= 1;
+                    if separate && depth == 0 {
+                        self.push(STMT_END.to_string(), span);
+                    }
+                }
+
+                for _ in 0..length {
+                    self.advance();
+                }
+                continue;
             }
+
+            if c.is_whitespace() {
+                self.flush();
+                self.advance();
+                continue;
+            }
+
+            if self.current.is_empty() {
+                self.current_span.replace(self.span(1));
+            }
+            self.current.push(c);
+            self.advance();
+        }
+
+        self.flush();
+        Ok((self.tokens, self.spans))
+    }
+}
+
+impl Lexer {
+    fn from_source(source: Source, separate: bool) -> Result<Lexer> {
+        if source.text.len() == 0 {
+            let ex = Ex::new("Can't parse an empty string");
+            return Err(ex.with_kind(ErrorKind::Parse));
         }
 
-        let tokens: Vec<String> = filtered
-            .trim()
-            .split_whitespace()
-            .map(|x| x.to_string())
-            .collect();
+        let original = source.text.clone();
+        let (tokens, spans) = Scanner::new(Rc::new(source))
+            .scan(separate)
+            .map_err(|ex| ex.with_kind(ErrorKind::Parse))?;
 
         let lex = Lexer {
             tokens,
+            spans,
             idx: 0,
             original,
             stack: vec![],
@@ -74,6 +293,15 @@ impl Lexer {
         Ok(lex)
     }
 
+    pub fn new(input: String) -> Result<Lexer> {
+        Lexer::from_source(Source::new(None, &input), false)
+    }
+
+    // top level expressions in a file don't need ';' between them
+    pub fn new_file(input: String, file_name: &str) -> Result<Lexer> {
+        Lexer::from_source(Source::new(Some(file_name), &input), true)
+    }
+
     pub fn to_vec(&self) -> Vec<String> {
         self.tokens.clone()
     }
@@ -89,6 +317,16 @@ impl Lexer {
             self.tokens.get(self.idx)
         }
     }
+
+    // span of the token peek() returns
+    pub fn peek_span(&self) -> Option<&Span> {
+        self.spans.get(self.idx)
+    }
+
+    // span of the token next() last returned
+    pub fn last_span(&self) -> Option<&Span> {
+        self.idx.checked_sub(1).and_then(|i| self.spans.get(i))
+    }
 }
 
 impl Iterator for Lexer {
@@ -108,7 +346,7 @@ impl Iterator for Lexer {
 
 #[cfg(test)]
 pub mod lexer_test {
-    use super::Lexer;
+    use super::{escape_string, unescape_string, Lexer};
     #[test]
     pub fn lexer_test_splits_whitespace() {
         let expr = String::from("     (    if ( eq n 0)\n\t( add a b )\n  )    ");
@@ -131,6 +369,92 @@ pub mod lexer_test {
         assert_eq!(expected.to_vec(), lex.tokens);
     }
 
+    #[test]
+    pub fn lexer_test_splits_braces() {
+        let expr = String::from("(get {\"a\" [1], k{x}} k)");
+        let expected = [
+            "(", "get", "{", "\"a\"", "[", "1", "]", "k", "{", "x", "}", "}", "k", ")",
+        ];
+        let lex = Lexer::new(expr).unwrap();
+        assert_eq!(expected.to_vec(), lex.tokens);
+    }
+
+    #[test]
+    pub fn lexer_test_strings() {
+        let expr = String::from("(concat \"a b\" \"(x, y)\"  \"say \\\"hi\\\"\")");
+        let expected = [
+            "(",
+            "concat",
+            "\"a b\"",
+            "\"(x, y)\"",
+            "\"say \\\"hi\\\"\"",
+            ")",
+        ];
+        let lex = Lexer::new(expr).unwrap();
+        assert_eq!(expected.to_vec(), lex.tokens);
+
+        assert_eq!(
+            unescape_string("\"say \\\"hi\\\"\\n\"").unwrap(),
+            "say \"hi\"\n"
+        );
+        assert_eq!(escape_string("say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+        assert!(unescape_string("\"\\q\"").is_err());
+
+        assert!(Lexer::new(String::from("(concat \"a b)")).is_err());
+    }
+
+    #[test]
+    pub fn lexer_test_spans() {
+        let expr = String::from("(add 1\n  \"a b\" #skip# (x))");
+        let mut lex = Lexer::new(expr).unwrap();
+        assert_eq!(
+            lex.to_vec(),
+            vec!["(", "add", "1", "\"a b\"", "(", "x", ")", ")"]
+        );
+
+        let mut positions = vec![];
+        while let Some(span) = lex.peek_span() {
+            positions.push((span.line, span.column, span.length));
+            lex.next();
+        }
+
+        let expected = vec![
+            (1, 1, 1),
+            (1, 2, 3),
+            (1, 6, 1),
+            (2, 3, 5),
+            (2, 16, 1),
+            (2, 17, 1),
+            (2, 18, 1),
+            (2, 19, 1),
+        ];
+        assert_eq!(positions, expected);
+        assert_eq!(lex.last_span().unwrap().column, 19);
+    }
+
+    #[test]
+    pub fn lexer_test_new_file() {
+        let expr = String::from("(def f (x) x)\n# (f 1) #\n(f 2) (f 3)");
+        let lex = Lexer::new_file(expr, "test.nv").unwrap();
+        let expected = [
+            "(", "def", "f", "(", "x", ")", "x", ")", ";", "(", "f", "2", ")", ";", "(", "f", "3",
+            ")", ";",
+        ];
+        assert_eq!(expected.to_vec(), lex.tokens);
+        assert_eq!(lex.spans[0].location(), "test.nv:1:1");
+    }
+
+    #[test]
+    pub fn lexer_test_new_file_literals() {
+        let expr = String::from("[1 2\n (add 1 2)]\n{\"k\" (f x)\n}");
+        let lex = Lexer::new_file(expr, "test.nv").unwrap();
+        let expected = [
+            "[", "1", "2", "(", "add", "1", "2", ")", "]", ";", "{", "\"k\"", "(", "f", "x", ")",
+            "}", ";",
+        ];
+        assert_eq!(expected.to_vec(), lex.tokens);
+    }
+
     #[test]
     pub fn lexer_test_iterator() {
         let expr = String::from("  ( let x 2 ) ");
@@ -23,6 +23,7 @@ use crate::{utils::constants::*, utils::file::*};
 use parser::parse_node::ParseValue;
 pub use utils::constants;
 pub use utils::message;
+pub use utils::message::{ErrorKind, Ex};
 
 pub use utils::file::{import_file, save_file, STL_FILE, USER_FILE};
 
@@ -30,6 +31,7 @@ use evaluator::context_tco::*;
 use evaluator::data_tco::*;
 use evaluator::evaluator_tco::*;
 use evaluator::function_tco::*;
+use evaluator::limits::{clear_interrupt, interrupt, BudgetGuard, EvalLimits};
 use lexer::{split_input, Lexer};
 use message::*;
 use parser::parse_node::ASTNode;
@@ -41,6 +43,7 @@ pub fn evaluate_one_node(
     node: Rc<ASTNode>,
     context: &mut evaluator::context_tco::EvalContext,
 ) -> Result<String> {
+    let _budget = BudgetGuard::start(context.get_limits());
     let res = evaluate_outer(context.clone(), node, true)?;
 
     let mut string = res.to_string();
@@ -91,8 +94,16 @@ pub struct EvalResult {
 }
 
 /// Evaluate top-level nodes in order and return result sturcts
+/// On failure, Ex::get_kind says what went wrong
 pub fn evaluate_all(inp: &str, context: &mut EvalContext) -> Result<Vec<EvalResult>> {
-    let lexed = lex!(inp);
+    let lexed = Lexer::new(inp.to_string())?;
+    evaluate_lexed(lexed, context)
+}
+
+/// Same as evaluate_all for an already lexed source e.g a file
+pub fn evaluate_lexed(lexed: Lexer, context: &mut EvalContext) -> Result<Vec<EvalResult>> {
+    // limits cover all the nodes together, not each one
+    let _budget = BudgetGuard::start(context.get_limits());
     let parse_nodes = parse_all(lexed)?;
     let mut results: Vec<EvalResult> = vec![];
 
@@ -151,7 +162,11 @@ pub fn process_command(command: &str, ctx: &mut EvalContext) -> Result<()> {
                 }
 
                 if let None = ctx.read().get_data_value(var) {
-                    return errf!("Identifier '{}' is not defined.", var);
+                    let msg = format!("Identifier '{}' is not defined.", var);
+                    let kind = ErrorKind::Name {
+                        name: var.to_string(),
+                    };
+                    return Err(Ex::new(&msg).with_kind(kind));
                 }
             }
 
@@ -183,6 +198,12 @@ pub fn process_command(command: &str, ctx: &mut EvalContext) -> Result<()> {
 pub fn nova_repl_tco(mut context: EvalContext) -> EvalContext {
     let mut rl = DefaultEditor::new().unwrap();
 
+    // Ctrl-C while evaluating stops that expression instead of the process
+    // (at the prompt rustyline reads it as a key instead)
+    if let Err(err) = ctrlc::set_handler(interrupt) {
+        println!("Couldn't set Ctrl-C handler: {}", err);
+    }
+
     println!();
     println!("Welcome to Nova: a highly expressive, dynamically typed functional programming language.\nType an expression to get started.\n");
 
@@ -208,9 +229,11 @@ pub fn nova_repl_tco(mut context: EvalContext) -> EvalContext {
 
                 rl.add_history_entry(inp.clone().trim()).unwrap();
 
+                clear_interrupt();
+
                 if inp.starts_with(CMD_PREFIX) {
                     if let Err(err) = process_command(&inp[1..], &mut context) {
-                        println!("{}", err.format_error());
+                        println!("{}", err.format_error_with_source());
                     }
                     continue;
                 }
@@ -227,7 +250,7 @@ pub fn nova_repl_tco(mut context: EvalContext) -> EvalContext {
                             println!("{}", res.result);
                         }
                     }
-                    Err(err) => println!("{}", err.format_error()),
+                    Err(err) => println!("{}", err.format_error_with_source()),
                 }
             }
 
@@ -250,19 +273,73 @@ pub fn print_eval_results() {
 // setup context by making the map of functions and pass it into Context::new, then pass it to nova_repl
 // this is how we can seed Context with map of refs to functions
 
+// flags for run: limits on each evaluation, e.g for untrusted scripts
+pub const MAX_STEPS_FLAG: &'static str = "--max-steps";
+pub const TIMEOUT_FLAG: &'static str = "--timeout"; // milliseconds
+pub const MAX_DEPTH_FLAG: &'static str = "--max-depth";
+pub const LAZY_FLAG: &str = "--lazy"; // call by need for functions defined in the run
+
+/// Split run's args into limits and strategy from flags and the remaining args
+/// e.g ["--lazy", "--max-steps", "1000", "file.txt"] => max_steps=1000, Lazy, ["file.txt"]
+pub fn parse_flags(args: Vec<String>) -> Result<(EvalLimits, Strategy, Vec<String>)> {
+    let mut limits = EvalLimits::default();
+    let mut strategy = Strategy::Strict;
+    let mut rest: Vec<String> = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == LAZY_FLAG {
+            strategy = Strategy::Lazy;
+            continue;
+        }
+
+        if ![MAX_STEPS_FLAG, TIMEOUT_FLAG, MAX_DEPTH_FLAG].contains(&arg.as_str()) {
+            rest.push(arg);
+            continue;
+        }
+
+        let value = match args.next().map(|x| x.parse::<u64>()) {
+            Some(Ok(value)) => value,
+            _ => return errf!("'{}' expected a non-negative number.", arg),
+        };
+
+        match arg.as_str() {
+            MAX_STEPS_FLAG => limits.max_steps = Some(value),
+            TIMEOUT_FLAG => limits.timeout = Some(std::time::Duration::from_millis(value)),
+            _ => limits.max_depth = Some(value as usize),
+        }
+    }
+
+    Ok((limits, strategy, rest))
+}
+
 // append new functions to end of user file
 // first arg: file path (optional)
 pub fn run(mut args: impl Iterator<Item = String>) {
     args.next(); // ignore first
     let args: Vec<String> = args.map(|x| x.to_string()).collect();
 
-    let mut ctx = evaluator::context_tco::EvalContext::new();
+    let (limits, strategy, args) = match parse_flags(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            println!("{}", err.format_error());
+            return;
+        }
+    };
+
+    let mut ctx = evaluator::context_tco::EvalContext::new()
+        .with_limits(limits)
+        .with_strategy(strategy);
 
     // cargo r "hello.txt"
     if args.len() == 1 {
         let file_name = args.get(0).unwrap();
         if let Err(error) = import_file(&file_name, &mut ctx) {
-            println!("Error when running file '{}': {}", file_name, error.format_error());
+            println!(
+                "Error when running file '{}': {}",
+                file_name,
+                error.format_error_with_source()
+            );
         }
         return;
     }
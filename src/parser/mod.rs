@@ -1,3 +1,4 @@
 pub mod parse_node;
+mod parse_operators;
 mod parse_special;
 pub mod parser;
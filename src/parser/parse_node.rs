@@ -1,4 +1,9 @@
 use crate::constants::*;
+use crate::evaluator::bigint::BigInt;
+use crate::evaluator::number::format_float;
+use crate::evaluator::rational::Rational;
+use crate::lexer::escape_string;
+use crate::utils::span::Span;
 
 use std::fmt::Display;
 use std::ops::Deref;
@@ -58,18 +63,68 @@ impl Display for FnDef {
     }
 }
 
-pub const LET_NODE_TYPE:&str="LetNode";
+// (data Shape (Circle r) (Rect w h) Empty): a type and its constructors with their fields
+#[derive(Debug, Clone)]
+pub struct DataDef {
+    pub name: String,
+    pub ctors: Vec<(String, Vec<String>)>,
+    pub global: bool,
+}
+
+impl DataDef {
+    pub fn set_global(&self, global: bool) -> DataDef {
+        DataDef {
+            name: self.name.clone(),
+            ctors: self.ctors.clone(),
+            global,
+        }
+    }
+}
+
+impl Display for DataDef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ctors: Vec<String> = self
+            .ctors
+            .iter()
+            .map(|(name, fields)| match fields.is_empty() {
+                true => name.clone(),
+                false => format!("{}{} {}{}", OPEN_EXPR, name, fields.join(SPACE), CLOSE_EXPR),
+            })
+            .collect();
+
+        write!(
+            f,
+            "{}{} {} {}{}",
+            OPEN_EXPR,
+            DATA_NAME,
+            self.name,
+            ctors.join(SPACE),
+            CLOSE_EXPR
+        )
+    }
+}
+
+pub const LET_NODE_TYPE: &str = "LetNode";
 
 #[derive(Debug, Display, Clone)]
 pub enum ParseValue {
     Symbol(String),
     Number(NumType),
+    BigNumber(Rc<BigInt>),  // literal too large for Number
+    Fraction(Rc<Rational>), // never a whole number
+    Decimal(f64),
+    StringLiteral(String), // unescaped contents
     ParseExpression(Vec<Rc<ASTNode>>),
     List(Vec<Rc<ASTNode>>),
+    Map(Vec<Rc<ASTNode>>), // keys and values alternate
     Boolean(bool),
     IfNode(Vec<Rc<ASTNode>>),
+    TryNode(Vec<Rc<ASTNode>>), // [body, name, handler]
     LetNode(Vec<Rc<ASTNode>>, bool),
     FnNode(FnDef),
+    LambdaNode(FnDef), // anonymous: name is always LAMBDA_TYPE
+    DataNode(DataDef),
+    MatchNode(Vec<Rc<ASTNode>>), // [value, pattern, body, pattern, body, ...]
     ParseUnit,
 }
 
@@ -112,6 +167,7 @@ pub struct ASTNode {
     pub parent: Option<Rc<ASTNode>>,
     pub original: Uuid,
     pub is_func: bool,
+    pub span: Option<Span>, // where the node starts in the source, if known
 }
 
 impl Clone for ASTNode {
@@ -121,6 +177,7 @@ impl Clone for ASTNode {
             parent: self.parent.clone(),
             original: Uuid::new_v4(),
             is_func: self.is_func,
+            span: self.span.clone(),
         }
     }
 }
@@ -149,14 +206,18 @@ impl ASTNode {
             parent: None,
             original: original_ref,
             is_func: false,
+            span: None,
         };
         let original = Rc::new(original);
 
         match &mut value {
             ParseExpression(ref mut children)
             | List(ref mut children)
+            | Map(ref mut children)
             | LetNode(ref mut children, _)
-            | IfNode(ref mut children) => {
+            | IfNode(ref mut children)
+            | TryNode(ref mut children)
+            | MatchNode(ref mut children) => {
                 let children = children.clone();
                 let mut children: Vec<ASTNode> =
                     children.into_iter().map(|r| r.as_ref().clone()).collect();
@@ -170,8 +231,11 @@ impl ASTNode {
                 let new_value = match value {
                     ParseExpression(_) => ParseExpression(children),
                     IfNode(_) => IfNode(children),
+                    TryNode(_) => TryNode(children),
+                    MatchNode(_) => MatchNode(children),
                     LetNode(_, global) => LetNode(children, global),
                     List(_) => List(children),
+                    Map(_) => Map(children),
                     _ => value, //unreachable
                 };
 
@@ -180,6 +244,7 @@ impl ASTNode {
                     parent: None,
                     original: original_ref,
                     is_func: false,
+                    span: None,
                 }
             }
             _ => ASTNode {
@@ -187,6 +252,7 @@ impl ASTNode {
                 parent: None,
                 original: original_ref,
                 is_func: false,
+                span: None,
             },
         }
     }
@@ -197,9 +263,16 @@ impl ASTNode {
             parent: self.parent.clone(),
             original: self.original,
             is_func: self.is_func,
+            span: self.span.clone(),
         }
     }
 
+    // keeps the id, so results still match up
+    pub fn with_span(mut self, span: Option<Span>) -> ASTNode {
+        self.span = span;
+        self
+    }
+
     pub fn empty() -> ASTNode {
         ASTNode::new(Symbol("Default parent".to_string()))
     }
@@ -242,23 +315,23 @@ impl ASTNode {
         match &self.value {
             Symbol(string) => string.clone(),
             Number(num) => num.to_string(),
-            ParseExpression(children) => {
-                let v: Vec<String> = children.iter().map(|n| n.to_string()).collect();
-                format!("{}{}{}", OPEN_EXPR, v.join(SPACE), CLOSE_EXPR)
-            }
-            List(children) => {
-                let v: Vec<String> = children.iter().map(|n| n.to_string()).collect();
-                format!("{}{}{}", OPEN_LIST, v.join(VAR_SEP), CLOSE_LIST)
-            }
-            IfNode(children) => {
-                let v: Vec<String> = children.iter().map(|n| n.to_string()).collect();
-                format!("{}{} {}{}", OPEN_EXPR, IF_NAME, v.join(SPACE), CLOSE_EXPR)
-            }
+            BigNumber(big) => big.to_string(),
+            Fraction(ratio) => ratio.to_string(),
+            Decimal(float) => format_float(*float),
+            StringLiteral(string) => escape_string(string),
+            // recursive: each form is written by its own function to keep this frame small
+            ParseExpression(children) => bracketed(OPEN_EXPR, None, children, SPACE, CLOSE_EXPR),
+            List(children) => bracketed(OPEN_LIST, None, children, VAR_SEP, CLOSE_LIST),
+            Map(children) => bracketed(OPEN_MAP, None, children, SPACE, CLOSE_MAP),
+            IfNode(children) => bracketed(OPEN_EXPR, Some(IF_NAME), children, SPACE, CLOSE_EXPR),
+            TryNode(children) => try_string(children),
             LetNode(children, _) => {
-                let v: Vec<String> = children.iter().map(|node| node.to_string()).collect();
-                format!("{}{} {}{}", OPEN_EXPR, LET_NAME, v.join(SPACE), CLOSE_EXPR)
+                bracketed(OPEN_EXPR, Some(LET_NAME), children, SPACE, CLOSE_EXPR)
             }
+            MatchNode(children) => match_string(children),
             FnNode(fn_def) => fn_def.to_string(),
+            DataNode(data_def) => data_def.to_string(),
+            LambdaNode(fn_def) => lambda_string(fn_def),
             Boolean(b) => {
                 if *b {
                     TRUE.to_string()
@@ -271,6 +344,57 @@ impl ASTNode {
     }
 }
 
+// children between brackets, after the keyword if there is one
+fn bracketed(
+    open: &str,
+    keyword: Option<&str>,
+    children: &[Rc<ASTNode>],
+    sep: &str,
+    close: &str,
+) -> String {
+    let v: Vec<String> = children.iter().map(|n| n.to_string()).collect();
+    match keyword {
+        Some(keyword) => format!("{}{} {}{}", open, keyword, v.join(sep), close),
+        None => format!("{}{}{}", open, v.join(sep), close),
+    }
+}
+
+fn try_string(children: &[Rc<ASTNode>]) -> String {
+    let v: Vec<String> = children.iter().map(|n| n.to_string()).collect();
+    let catch = format!(
+        "{}{} {} {}{}",
+        OPEN_EXPR, CATCH_NAME, v[1], v[2], CLOSE_EXPR
+    );
+    format!("{}{} {} {}{}", OPEN_EXPR, TRY_NAME, v[0], catch, CLOSE_EXPR)
+}
+
+fn match_string(children: &[Rc<ASTNode>]) -> String {
+    let clauses: Vec<String> = children[1..]
+        .chunks(2)
+        .map(|clause| format!("{}{} {}{}", OPEN_EXPR, clause[0], clause[1], CLOSE_EXPR))
+        .collect();
+    format!(
+        "{}{} {} {}{}",
+        OPEN_EXPR,
+        MATCH_NAME,
+        children[0],
+        clauses.join(SPACE),
+        CLOSE_EXPR
+    )
+}
+
+fn lambda_string(fn_def: &FnDef) -> String {
+    let body: Vec<String> = fn_def.body.iter().map(|n| n.to_string()).collect();
+    format!(
+        "{}{} {} {}{}",
+        OPEN_EXPR,
+        fn_def.params.join(SPACE),
+        LAMBDA,
+        body.join(SPACE),
+        CLOSE_EXPR
+    )
+}
+
 impl Deref for ASTNode {
     type Target = ParseValue;
     fn deref(&self) -> &Self::Target {
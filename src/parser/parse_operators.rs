@@ -0,0 +1,166 @@
+use std::rc::Rc;
+
+use crate::constants::*;
+use crate::message::*;
+use crate::parser::parse_node::*;
+
+use super::parser::make_expression;
+
+// Operator precedence, loosest first:
+// 1. x >> f a       => (f a x)          pipe, left-assoc
+// 2. f $ g x        => (f (g x))        application, right-assoc
+// 3. f @ g          => (@ -> (f (g @))) composition: apply g then f
+// 4. f a b          => (f a b)          application by spaces/parentheses
+// $ and >> both pass the argument last, so (range 5 10) >> for_each $ puts
+// is (for_each puts (range 5 10)) and lst >> map f is (map f lst)
+
+fn is_operator(node: &ASTNode, operator: &str) -> bool {
+    match &node.value {
+        Symbol(sym) => sym.eq(operator),
+        _ => false,
+    }
+}
+
+pub(super) fn has_operator(nodes: &[Rc<ASTNode>]) -> bool {
+    nodes
+        .iter()
+        .any(|node| OPERATORS.iter().any(|op| is_operator(node, op)))
+}
+
+// split nodes on operator, rejecting empty operands e.g (>> f) or (f $)
+fn split_on(nodes: Vec<Rc<ASTNode>>, operator: &str) -> Result<Vec<Vec<Rc<ASTNode>>>> {
+    let mut segments: Vec<Vec<Rc<ASTNode>>> = vec![vec![]];
+
+    for node in nodes {
+        if is_operator(&node, operator) {
+            segments.push(vec![]);
+        } else {
+            segments.last_mut().unwrap().push(node);
+        }
+    }
+
+    if segments.iter().any(|seg| seg.is_empty()) {
+        return errf!("'{}' expected an expression on both sides.", operator);
+    }
+
+    Ok(segments)
+}
+
+// one node stays as is, more than one is a function call
+fn application(nodes: Vec<Rc<ASTNode>>) -> Rc<ASTNode> {
+    if nodes.len() == 1 {
+        nodes.into_iter().next().unwrap()
+    } else {
+        make_expression(nodes)
+    }
+}
+
+// (f a) x => (f a x): the argument goes last so curried calls stay flat
+fn call_with(func: Rc<ASTNode>, arg: Rc<ASTNode>) -> Rc<ASTNode> {
+    let children = match &func.value {
+        ParseExpression(children) => {
+            let mut new_children = children.clone();
+            new_children.push(arg);
+            new_children
+        }
+        _ => vec![func, arg],
+    };
+
+    make_expression(children)
+}
+
+// rewrite a sequence of nodes containing $, >> or @ into a single node
+pub(super) fn parse_operators(nodes: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    parse_pipe(nodes)
+}
+
+fn parse_pipe(nodes: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    let mut segments = split_on(nodes, PIPE)?.into_iter();
+    let mut acc = parse_dollar(segments.next().unwrap())?;
+
+    for segment in segments {
+        let func = parse_dollar(segment)?;
+        acc = call_with(func, acc);
+    }
+
+    Ok(acc)
+}
+
+fn parse_dollar(nodes: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    let mut segments = split_on(nodes, COMP_OPR)?;
+    let mut acc = parse_compose(segments.pop().unwrap())?;
+
+    for segment in segments.into_iter().rev() {
+        let func = parse_compose(segment)?;
+        acc = call_with(func, acc);
+    }
+
+    Ok(acc)
+}
+
+fn parse_compose(nodes: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    let segments = split_on(nodes, COMP_LEFT)?;
+
+    if segments.len() == 1 {
+        let segment = segments.into_iter().next().unwrap();
+        return Ok(application(segment));
+    }
+
+    // the parameter can't clash with a user variable: '@' is reserved
+    let mut body = Rc::new(ASTNode::new(Symbol(COMP_LEFT.to_string())));
+    for segment in segments.into_iter().rev() {
+        body = make_expression(vec![application(segment), body]);
+    }
+
+    let lambda = LambdaNode(FnDef {
+        name: LAMBDA_TYPE.to_string(),
+        params: vec![COMP_LEFT.to_string()],
+        body: vec![body],
+        global: false,
+    });
+
+    Ok(Rc::new(ASTNode::new(lambda)))
+}
+
+#[cfg(test)]
+use super::parser::tests::parse_one;
+
+#[test]
+fn parse_pipe_test() {
+    assert_eq!(parse_one("x >> f"), "(f x)");
+    assert_eq!(parse_one("x >> f a >> g"), "(g (f a x))");
+    assert_eq!(parse_one("(lst >> (map f))"), "(map f lst)");
+    assert_eq!(
+        parse_one("(range 5 10) >> for_each $ puts"),
+        "(for_each puts (range 5 10))"
+    );
+    assert_eq!(parse_one("x >> f $ g y >> h"), "(h (f (g y) x))");
+}
+
+#[test]
+fn parse_dollar_test() {
+    assert_eq!(parse_one("(not $ (nil? lst))"), "(not (nil? lst))");
+    assert_eq!(parse_one("f $ g $ h x"), "(f (g (h x)))");
+    assert_eq!(parse_one("map f $ cdr lst"), "(map f (cdr lst))");
+}
+
+#[test]
+fn parse_compose_test() {
+    assert_eq!(parse_one("(f @ g)"), "(@ -> (f (g @)))");
+    assert_eq!(parse_one("map f @ cdr"), "(@ -> ((map f) (cdr @)))");
+    assert_eq!(parse_one("f@g $ x"), "((@ -> (f (g @))) x)");
+    assert_eq!(parse_one("x >> f @ g"), "((@ -> (f (g @))) x)");
+}
+
+#[test]
+fn parse_operators_test_err() {
+    use crate::lex;
+    use crate::lexer::Lexer;
+    use crate::parser::parser::parse;
+
+    for expr in ["(>> f)", "(x >>)", "(f $)", "(f @ @ g)"] {
+        let mut l = lex!(expr);
+        let err = parse(&mut l).unwrap_err().format_error();
+        assert!(err.contains("expected an expression on both sides"));
+    }
+}
@@ -6,6 +6,7 @@ use crate::message::*;
 use crate::parser::parse_node::*;
 use crate::{lex, lexer};
 
+use super::parse_operators::{has_operator, parse_operators};
 use super::parser::tests::test_parse;
 use super::parser::*;
 
@@ -18,9 +19,71 @@ pub(super) fn parse_special(
         Special::If => return parse_if_expression(children),
         Special::Let => return parse_let_expression(children, global),
         Special::Fn => return parse_fn_def(children, global),
+        Special::Lambda => return parse_lambda(children),
+        Special::Try => return parse_try_expression(children),
+        Special::Data => return parse_data_def(children, global),
+        Special::Match => return parse_match_expression(children),
     }
 }
 
+// x -> y -> body, or x y -> body: params before the last arrow, body after it
+pub(super) fn parse_lambda(children: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    let mut segments: Vec<Vec<Rc<ASTNode>>> = vec![vec![]];
+
+    for child in children {
+        match &child.value {
+            Symbol(sym) if sym.eq(LAMBDA) => segments.push(vec![]),
+            _ => segments.last_mut().unwrap().push(child),
+        }
+    }
+
+    let body = segments.pop().unwrap();
+    if body.is_empty() {
+        let msg = format!("Expected an expression after '{}'.", LAMBDA);
+        return err!(msg);
+    }
+
+    let mut params: Vec<String> = vec![];
+    for segment in segments {
+        if segment.is_empty() {
+            let msg = format!("Expected parameters before '{}'.", LAMBDA);
+            return err!(msg);
+        }
+
+        for node in segment {
+            match node.get_symbol() {
+                Some(sym) => params.push(is_valid_identifier(sym.as_str())?),
+                None => {
+                    let msg = format!(
+                        "Lambda parameters should contain only symbols but got '{}'.",
+                        node.to_string()
+                    );
+                    return err!(msg);
+                }
+            }
+        }
+    }
+
+    // x -> add x 1 is the same as x -> (add x 1)
+    // lambda binds loosest: x -> x >> f is x -> (x >> f)
+    let body = if body.len() == 1 {
+        body.into_iter().next().unwrap()
+    } else if has_operator(&body) {
+        parse_operators(body)?
+    } else {
+        make_expression(body)
+    };
+
+    let lambda = LambdaNode(FnDef {
+        name: LAMBDA_TYPE.to_string(),
+        params,
+        body: vec![body],
+        global: false,
+    });
+
+    Ok(Rc::new(ASTNode::new(lambda)))
+}
+
 // create FnNode
 // def, name, args, body
 pub(super) fn parse_fn_def(children: Vec<Rc<ASTNode>>, global: bool) -> Result<Rc<ASTNode>> {
@@ -110,6 +173,164 @@ pub(super) fn parse_if_expression(children: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNo
     Ok(Rc::new(ASTNode::new(node_val)))
 }
 
+// (try body (catch e handler)) => TryNode([body, e, handler])
+pub(super) fn parse_try_expression(children: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    let usage = format!(
+        "'{}' should look like ({} expr ({} name expr)).",
+        TRY_NAME, TRY_NAME, CATCH_NAME
+    );
+
+    if children.len() != 3 {
+        return err!(usage);
+    }
+
+    let body = Rc::clone(&children[1]);
+    let catch = match children[2].get_expression() {
+        Some(catch) if catch.len() == 3 => catch,
+        _ => return err!(usage),
+    };
+
+    if catch[0].get_symbol().as_deref() != Some(CATCH_NAME) {
+        return err!(usage);
+    }
+
+    let name = match catch[1].get_symbol() {
+        Some(name) => is_valid_identifier(name.as_str())?,
+        None => {
+            let msg = format!(
+                "'{}' expected a name but got '{}'.",
+                CATCH_NAME,
+                catch[1].to_string()
+            );
+            return err!(msg);
+        }
+    };
+
+    let name = Rc::new(ASTNode::new(Symbol(name)));
+    let handler = Rc::clone(&catch[2]);
+
+    Ok(Rc::new(ASTNode::new(TryNode(vec![body, name, handler]))))
+}
+
+// (data Shape (Circle r) (Rect w h) Empty): a constructor is a name, or a name and its fields
+pub(super) fn parse_data_def(children: Vec<Rc<ASTNode>>, global: bool) -> Result<Rc<ASTNode>> {
+    if children.len() < 3 {
+        let msg = format!(
+            "'{}' should have a type name and at least one constructor.",
+            DATA_NAME
+        );
+        return err!(msg);
+    }
+
+    let name = match children[1].get_symbol() {
+        Some(name) => is_valid_identifier(name.as_str())?,
+        None => {
+            let msg = format!(
+                "'{}' expected a type name but got '{}'.",
+                DATA_NAME, children[1]
+            );
+            return err!(msg);
+        }
+    };
+
+    let mut ctors: Vec<(String, Vec<String>)> = vec![];
+    for node in children.iter().skip(2) {
+        let parts = node.get_expression().unwrap_or(vec![Rc::clone(node)]);
+        let symbols: Option<Vec<String>> = parts.iter().map(|p| p.get_symbol()).collect();
+
+        let mut symbols = match symbols {
+            Some(symbols) => symbols.into_iter(),
+            None => {
+                let msg = format!(
+                    "Constructors should contain only symbols but got '{}'.",
+                    node
+                );
+                return err!(msg);
+            }
+        };
+
+        let ctor = is_valid_identifier(symbols.next().unwrap().as_str())?;
+        if ctors.iter().any(|(existing, _)| existing.eq(&ctor)) {
+            let msg = format!("'{}' defines the constructor '{}' twice.", name, ctor);
+            return err!(msg);
+        }
+
+        let fields: Result<Vec<String>> = symbols.map(|s| is_valid_identifier(&s)).collect();
+        ctors.push((ctor, fields?));
+    }
+
+    let data_node = DataNode(DataDef {
+        name,
+        ctors,
+        global,
+    });
+    Ok(Rc::new(ASTNode::new(data_node)))
+}
+
+// _, names to bind, literals, [p1 p2], (cons head tail) and (Ctor p1 p2)
+fn check_pattern(pattern: &Rc<ASTNode>) -> Result<()> {
+    match &pattern.value {
+        Symbol(sym) if sym.eq(WILDCARD) => Ok(()),
+        Symbol(sym) => is_valid_identifier(sym).map(|_| ()),
+        Number(_) | BigNumber(_) | Fraction(_) | Decimal(_) | Boolean(_) | StringLiteral(_) => {
+            Ok(())
+        }
+        List(children) => children.iter().try_for_each(check_pattern),
+        ParseExpression(children) => {
+            let name = children.first().and_then(|node| node.get_symbol());
+            match name.as_deref() {
+                Some(CONS) if children.len() != 3 => {
+                    let msg = format!("'{}' patterns should look like ({} head tail).", CONS, CONS);
+                    err!(msg)
+                }
+                Some(CONS) => children[1..].iter().try_for_each(check_pattern),
+                Some(ctor) => {
+                    is_valid_identifier(ctor)?;
+                    children[1..].iter().try_for_each(check_pattern)
+                }
+                None => {
+                    let msg = format!("'{}' is not a valid pattern.", pattern);
+                    err!(msg)
+                }
+            }
+        }
+        _ => {
+            let msg = format!("'{}' is not a valid pattern.", pattern);
+            err!(msg)
+        }
+    }
+}
+
+// (match value (pattern body) ...) => MatchNode([value, pattern, body, ...])
+pub(super) fn parse_match_expression(children: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+    if children.len() < 3 {
+        let msg = format!(
+            "'{}' should look like ({} expr (pattern expr) ...).",
+            MATCH_NAME, MATCH_NAME
+        );
+        return err!(msg);
+    }
+
+    let mut nodes = vec![Rc::clone(&children[1])];
+    for clause in children.iter().skip(2) {
+        let clause_nodes = match clause.get_expression() {
+            Some(nodes) if nodes.len() == 2 => nodes,
+            _ => {
+                let msg = format!(
+                    "'{}' expected a (pattern expr) pair but got '{}'.",
+                    MATCH_NAME, clause
+                );
+                return err!(msg);
+            }
+        };
+
+        check_pattern(&clause_nodes[0])?;
+        nodes.extend(clause_nodes);
+    }
+
+    Ok(Rc::new(ASTNode::new(MatchNode(nodes))))
+}
+
 // change to return tuple (ident, expr) since we are checking anyway
 pub(super) fn parse_let_expression(
     children: Vec<Rc<ASTNode>>,
@@ -140,6 +361,116 @@ pub fn parse_let_test() {
     test_parse(vec![e1, e2, e3, e4]);
 }
 
+#[test]
+fn parse_try_test() {
+    let exprs = vec![
+        "(try (car lst) (catch e 0))",
+        "(try x (catch err (puts (err-msg err))))",
+    ];
+    test_parse(exprs);
+
+    let mut l = lex!("(try (car lst))");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(try (car lst) (handle e 0))");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(try (car lst) (catch 2 0))");
+    assert!(parse(&mut l).is_err());
+}
+
+#[test]
+fn parse_data_test() {
+    test_parse(vec![
+        "(data Shape (Circle r) (Rect w h) Empty)",
+        "(data Bit On Off)",
+    ]);
+
+    let mut l = lex!("(data Shape)");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(data Shape (Circle 2))");
+    assert!(parse(&mut l)
+        .err()
+        .unwrap()
+        .format_error()
+        .contains("only symbols"));
+
+    let mut l = lex!("(data Bit On On)");
+    assert!(parse(&mut l)
+        .err()
+        .unwrap()
+        .format_error()
+        .contains("twice"));
+
+    let mut l = lex!("(data Shape (if r))");
+    assert!(parse(&mut l).is_err());
+}
+
+#[test]
+fn parse_match_test() {
+    let exprs = vec![
+        "(match s ((Circle r) (mul r r)) ((Rect w h) (mul w h)))",
+        "(match lst ([] 0) ((cons h t) h))",
+        "(match n (0 true) (1.5 false) (_ n))",
+        "(match p ([a,[b,c]] (add a b c)) (Empty 0))",
+    ];
+    test_parse(exprs);
+
+    let mut l = lex!("(match x)");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(match x (1 2 3))");
+    assert!(parse(&mut l)
+        .err()
+        .unwrap()
+        .format_error()
+        .contains("(pattern expr) pair"));
+
+    let mut l = lex!("(match x ((cons h) h))");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(match x ({1 2} 0))");
+    assert!(parse(&mut l)
+        .err()
+        .unwrap()
+        .format_error()
+        .contains("not a valid pattern"));
+}
+
+#[test]
+fn parse_lambda_test() {
+    let exprs = vec![
+        "(x -> (add x 1))",
+        "(x y -> (add x y))",
+        "(map (x -> (mul x 2)) lst)",
+        "((x y -> (add x y)) 1 2)",
+    ];
+    test_parse(exprs);
+
+    // chained arrows and unbracketed bodies
+    let mut l = lex!("(x->y->(add x y)) (1) (2)");
+    let p = parse(&mut l).unwrap().to_string();
+    assert_eq!(p, "((x y -> (add x y)) 1 2)");
+
+    let mut l = lex!("(z, y -> add z y)");
+    let p = parse(&mut l).unwrap().to_string();
+    assert_eq!(p, "(z y -> (add z y))");
+
+    let mut l = lex!("(x ->)");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(-> x)");
+    assert!(parse(&mut l).is_err());
+
+    let mut l = lex!("(2 -> x)");
+    assert!(parse(&mut l)
+        .err()
+        .unwrap()
+        .format_error()
+        .contains("only symbols"));
+}
+
 #[test]
 fn parse_if_test() {
     let exprs = vec![
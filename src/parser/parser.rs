@@ -1,9 +1,14 @@
 use std::rc::Rc;
 
+use super::parse_operators::{has_operator, parse_operators};
 use super::parse_special::*;
 
 use crate::constants::*;
+use crate::evaluator::bigint::BigInt;
+use crate::evaluator::number::parse_float;
+use crate::evaluator::rational::Rational;
 use crate::lexer;
+use crate::lexer::{is_string_token, unescape_string};
 use crate::message::*;
 use crate::parser::parse_node::*;
 
@@ -11,6 +16,10 @@ pub(super) enum Special {
     If,
     Let,
     Fn,
+    Lambda,
+    Try,
+    Data,
+    Match,
 }
 
 use Special::*;
@@ -22,9 +31,20 @@ impl Special {
             IF_NAME => Some(If),
             LET_NAME => Some(Let),
             FN_NAME => Some(Fn),
+            TRY_NAME => Some(Try),
+            DATA_NAME => Some(Data),
+            MATCH_NAME => Some(Match),
             _ => None,
         }
     }
+
+    // x -> body: the arrow can be anywhere in the expression, not just first
+    fn is_lambda(children: &[Rc<ASTNode>]) -> bool {
+        children.iter().any(|node| match &node.value {
+            Symbol(sym) => sym.eq(LAMBDA),
+            _ => false,
+        })
+    }
 }
 
 macro_rules! try_spec {
@@ -33,16 +53,20 @@ macro_rules! try_spec {
         if try_special.is_some() {
             return parse_special(try_special.unwrap(), $vec, $global);
         }
+
+        if Special::is_lambda(&$vec) {
+            return parse_special(Lambda, $vec, $global);
+        }
     };
 }
 
 pub const EMPTY_MSG: &'static str = "Can't parse empty expression";
 
 fn get_brackets_error(s: String, open_token: &str) -> String {
-    let close_token = if open_token.eq(OPEN_EXPR) {
-        CLOSE_EXPR
-    } else {
-        CLOSE_LIST
+    let close_token = match open_token {
+        OPEN_EXPR => CLOSE_EXPR,
+        OPEN_MAP => CLOSE_MAP,
+        _ => CLOSE_LIST,
     };
     let open_token = open_token.to_string();
 
@@ -76,7 +100,7 @@ fn parse_list_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
         children.push(res);
     };
 
-    // empty case: () => Unit, [] => list
+    // empty case: () => Unit, [] => list, {} => map
     if children.len() == 0 {
         lex.next();
         let expr = if open_token.eq(OPEN_EXPR) { "()" } else { "[]" };
@@ -86,8 +110,14 @@ fn parse_list_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
             return Ok(Rc::new(u));
         }
 
+        // [] => empty list
         if open_token.eq(OPEN_LIST) {
-            // handle nil here
+            let nil = ASTNode::new(List(vec![]));
+            return Ok(Rc::new(nil));
+        }
+
+        if open_token.eq(OPEN_MAP) {
+            return Ok(Rc::new(ASTNode::new(Map(vec![]))));
         }
 
         return err!(format!("{}: '{}'", EMPTY_MSG, expr));
@@ -103,7 +133,7 @@ fn parse_list_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
             }
 
             let cmp = (open_token.as_str(), last_token);
-            if cmp != EXPR_TUP && cmp != LIST_TUP {
+            if cmp != EXPR_TUP && cmp != LIST_TUP && cmp != MAP_TUP {
                 // return bracket mismatch and index
                 let msg = format!(
                     "Mismatched brackets: '{}' for '{}' at index {}.",
@@ -126,29 +156,51 @@ fn parse_list_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
         let node = children.into_iter().next().unwrap();
         return Ok(node);
     }
+
+    // list elements are plain values: no special forms or function position
+    if open_token == OPEN_LIST {
+        return Ok(Rc::new(ASTNode::new(List(children))));
+    }
+
+    // {k1 v1 k2 v2}: keys and values alternate
+    if open_token == OPEN_MAP {
+        if !children.len().is_multiple_of(2) {
+            let msg = format!(
+                "Map literal has a key without a value: '{}'.",
+                children.last().unwrap()
+            );
+            return err!(msg);
+        }
+        return Ok(Rc::new(ASTNode::new(Map(children))));
+    }
     // try_spec: bool for global
     // global means whether to take return value to set in outer ctx
     // false: expr just returns normal value
 
     try_spec!(children, false); // this macro returns out of the function if match
 
+    if has_operator(&children) {
+        return parse_operators(children);
+    }
+
+    Ok(make_expression(children))
+}
+
+// function call expression from non-empty children
+pub(super) fn make_expression(children: Vec<Rc<ASTNode>>) -> Rc<ASTNode> {
     // mark first as func call
     // let first = children.get_mut(0).unwrap();
     // todo: change to use RefCell<ASTNode> internally then only return Rc at the top level
     let mut children_nodes: Vec<ASTNode> =
         children.into_iter().map(|r| r.as_ref().clone()).collect();
     let first = children_nodes.first_mut().unwrap();
-    first.is_func = true;
+    // a list literal is never a function call, even in first position
+    first.is_func = !matches!(first.value, List(_) | Map(_));
 
+    let span = first.span.clone();
     let children = children_nodes.into_iter().map(|n| Rc::new(n)).collect();
 
-    let node_val = if open_token == OPEN_EXPR {
-        ParseExpression(children)
-    } else {
-        List(children)
-    };
-
-    Ok(Rc::new(ASTNode::new(node_val)))
+    Rc::new(ASTNode::new(ParseExpression(children)).with_span(span))
 }
 
 pub fn parse_atomic_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
@@ -160,6 +212,11 @@ pub fn parse_atomic_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
 
     let token = token_opt.unwrap();
 
+    if is_string_token(&token) {
+        let string = unescape_string(&token)?;
+        return Ok(Rc::new(ASTNode::new(StringLiteral(string))));
+    }
+
     if token.eq(TRUE) {
         return Ok(Rc::new(ASTNode::new(Boolean(true))));
     } else if token.eq(FALSE) {
@@ -171,14 +228,39 @@ pub fn parse_atomic_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
     let node = match try_numeric {
         Ok(num) => Rc::new(ASTNode::new(Number(num))),
 
-        Err(_) => Rc::new(ASTNode::new(Symbol(token))),
+        Err(_) => match (
+            BigInt::parse(&token),
+            Rational::parse(&token),
+            parse_float(&token),
+        ) {
+            (Some(big), _, _) => Rc::new(ASTNode::new(BigNumber(Rc::new(big)))),
+            // 4/2 is just 2
+            (None, Some(ratio), _) if ratio.is_integer() => match ratio.numer().to_i64() {
+                Some(num) => Rc::new(ASTNode::new(Number(num))),
+                None => Rc::new(ASTNode::new(BigNumber(Rc::new(ratio.numer().clone())))),
+            },
+            (None, Some(ratio), _) => Rc::new(ASTNode::new(Fraction(Rc::new(ratio)))),
+            (None, None, Some(float)) => Rc::new(ASTNode::new(Decimal(float))),
+            (None, None, None) => Rc::new(ASTNode::new(Symbol(token))),
+        },
     };
 
     Ok(node)
 }
 
 // recursive
+// the node and any error get the span of the token the expression starts at
 pub fn parse_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
+    let span = lex.peek_span().cloned();
+
+    match parse_expression_inner(lex) {
+        Ok(node) if node.span.is_none() => Ok(Rc::new(node.copy().with_span(span))),
+        Ok(node) => Ok(node),
+        Err(ex) => Err(ex.with_span(span)),
+    }
+}
+
+fn parse_expression_inner(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
     let token_peek = lex.peek();
     if let None = token_peek {
         return err!(format!("Empty expression at index {}.", lex.idx));
@@ -202,6 +284,11 @@ pub fn parse_expression(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
 // for now: return first ASTNode
 // once curried functions: do evaluation in order
 pub fn parse(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
+    parse_statement(lex).map_err(|ex| ex.with_kind(ErrorKind::Parse))
+}
+
+// one top level statement, up to the next ';'
+fn parse_statement(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
     let mut nodes: Vec<Rc<ASTNode>> = Vec::new();
 
     loop {
@@ -252,13 +339,24 @@ pub fn parse(lex: &mut lexer::Lexer) -> Result<Rc<ASTNode>> {
                 new_node.value = FnNode(new_def);
                 Rc::new(new_node)
             }
+            DataNode(def) => {
+                let mut new_node = node.as_ref().clone();
+                new_node.value = DataNode(def.set_global(true));
+                Rc::new(new_node)
+            }
             _ => node,
         }
     } else {
         // if special: return that, otherwise make expr with nodes
         // global true: so that 'let' without brackets can be used for var assignment
         try_spec!(nodes_filtered, true);
-        Rc::new(ASTNode::new(ParseExpression(nodes_filtered)))
+
+        if has_operator(&nodes_filtered) {
+            parse_operators(nodes_filtered)?
+        } else {
+            let span = nodes_filtered[0].span.clone();
+            Rc::new(ASTNode::new(ParseExpression(nodes_filtered)).with_span(span))
+        }
     };
 
     Ok(root)
@@ -328,16 +426,22 @@ pub mod tests {
         assert_eq!(parse_one("add 2 2"), "(add 2 2)");
         assert_eq!(
             parse_one("(fn $ map fn lst) (add 2 3) (sub 3 5)"),
-            "((fn $ map fn lst) (add 2 3) (sub 3 5))"
+            "((fn (map fn lst)) (add 2 3) (sub 3 5))"
+        );
+
+        // operators are rewritten into calls
+        assert_eq!(
+            parse_one("(map (sum fn (add 2 3)) >> (rec (add 2 3) lst))"),
+            "(rec (add 2 3) lst (map (sum fn (add 2 3))))"
         );
 
         let exps = vec![
             "(sum (map lst (take 5)) (succ 5) [1,2])",
             // "(if (eq n 0) (recr (pred n)) (recr (succ n)))",
-            "(map (sum fn (add 2 3)) >> (rec (add 2 3) lst))",
             "sum",
             "[2]",
             "[1,2,(add 5 6),[3,4,[5,6,(sub 4 5)]]]",
+            "(concat \"a b\" \"\\\"quoted\\\"\\n\")",
         ];
 
         test_parse(exps);
@@ -407,6 +511,16 @@ pub mod tests {
         } else {
             assert!(false);
         }
+
+        // [] is the empty list, not an error
+        let lex = &mut Lexer::new("[]".to_string()).unwrap();
+        let res = parse_list_expression(lex).unwrap();
+
+        if let ParseValue::List(vc) = &res.value {
+            assert!(vc.is_empty());
+        } else {
+            assert!(false);
+        }
     }
 
     #[test]
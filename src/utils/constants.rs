@@ -6,8 +6,12 @@ pub const TAB: &str = "\t";
 pub const VAR_SEP: &str = ",";
 pub const OPEN_LIST: &str = "[";
 pub const CLOSE_LIST: &str = "]";
+pub const OPEN_MAP: &str = "{";
+pub const CLOSE_MAP: &str = "}";
 pub const SPACE: &str = " ";
 pub const EMPTY: &str = "\0";
+pub const QUOTE: &str = "\"";
+pub const ESCAPE: char = '\\';
 
 pub type NumType = i64;
 
@@ -15,6 +19,11 @@ pub type NumType = i64;
 pub const LET_NAME: &str = "let";
 pub const IF_NAME: &str = "if";
 pub const FN_NAME: &str = "def";
+pub const TRY_NAME: &str = "try";
+pub const CATCH_NAME: &str = "catch";
+pub const DATA_NAME: &str = "data";
+pub const MATCH_NAME: &str = "match";
+pub const WILDCARD: &str = "_"; // pattern matching anything in match
 
 // Operations
 pub const ADD: &str = "add";
@@ -34,9 +43,46 @@ pub const SET: &str = "set";
 pub const GET: &str = "get";
 pub const LT: &str = "lt";
 pub const GT: &str = "gt";
+pub const LE: &str = "le";
+pub const GE: &str = "ge";
+pub const NOT_EQUALS: &str = "neq";
+pub const NOT: &str = "not";
 pub const MOD: &str = "mod";
 pub const DIV: &str = "div";
 
+// Numbers
+pub const FLOOR: &str = "floor";
+pub const CEIL: &str = "ceil";
+pub const ROUND: &str = "round";
+pub const TO_INT: &str = "to-int";
+pub const TO_FLOAT: &str = "to-float";
+
+// Math
+pub const ABS: &str = "abs";
+pub const MIN: &str = "min";
+pub const MAX: &str = "max";
+pub const POW: &str = "pow";
+pub const SQRT: &str = "sqrt";
+pub const GCD: &str = "gcd";
+pub const LCM: &str = "lcm";
+pub const SIGN: &str = "sign";
+pub const EVEN: &str = "even?";
+pub const ODD: &str = "odd?";
+pub const SIN: &str = "sin";
+pub const COS: &str = "cos";
+pub const TAN: &str = "tan";
+pub const ASIN: &str = "asin";
+pub const ACOS: &str = "acos";
+pub const ATAN: &str = "atan";
+pub const EXP: &str = "exp";
+pub const LOG: &str = "log";
+pub const LOG10: &str = "log10";
+pub const BAND: &str = "band";
+pub const BOR: &str = "bor";
+pub const BXOR: &str = "bxor";
+pub const SHL: &str = "shl";
+pub const SHR: &str = "shr";
+
 // Boolean
 pub const TRUE: &str = "true";
 pub const FALSE: &str = "false";
@@ -51,10 +97,33 @@ pub const LCAR: &str = "lcar";
 pub const INDEX: &str = "idx";
 pub const EMPTY_LIST: &str = "[]";
 
+// Map
+pub const DEL: &str = "del";
+pub const KEYS: &str = "keys";
+pub const VALUES: &str = "values";
+pub const HAS: &str = "has?";
+
+// String
+pub const CONCAT: &str = "concat";
+pub const LENGTH: &str = "length";
+pub const SUBSTRING: &str = "substring";
+pub const SPLIT: &str = "split";
+pub const JOIN: &str = "join";
+pub const TO_NUMBER: &str = "to-number";
+pub const TO_STRING: &str = "to-string";
+
+// Errors
+pub const ERROR: &str = "error";
+pub const ERR_MSG: &str = "err-msg";
+pub const ERR_KIND: &str = "err-kind";
+
 // builtins list
-pub const BUILTINS: [&'static str; 26] = [
-    ADD, MULT, SUB, DBL, INC, DEC, EQUALS, PUTS, PRINT, OR, AND, IMPORT, CHAIN, SET, GET, LT, GT,
-    MOD, DIV, CONS, CAR, CDR, LCONS, LCDR, LCAR, INDEX,
+pub const BUILTINS: [&'static str; 71] = [
+    ADD, MULT, SUB, INC, DEC, EQUALS, PUTS, PRINT, OR, AND, CHAIN, SET, GET, LT, GT, LE, GE,
+    NOT_EQUALS, NOT, MOD, DIV, FLOOR, CEIL, ROUND, TO_INT, TO_FLOAT, ABS, MIN, MAX, POW, SQRT, GCD,
+    LCM, SIGN, EVEN, ODD, SIN, COS, TAN, ASIN, ACOS, ATAN, EXP, LOG, LOG10, BAND, BOR, BXOR, SHL,
+    SHR, CONS, CAR, CDR, LCONS, LCDR, LCAR, INDEX, DEL, KEYS, VALUES, HAS, CONCAT, LENGTH,
+    SUBSTRING, SPLIT, JOIN, TO_NUMBER, TO_STRING, ERROR, ERR_MSG, ERR_KIND,
 ];
 
 // Lambda
@@ -68,22 +137,29 @@ pub const PIPE: &str = ">>";
 pub const STMT_END: &str = ";";
 
 // Some useful token arrays
-pub const SPLIT_TOKENS: [&'static str; 12] = [
-    OPEN_EXPR, CLOSE_EXPR, NEWLINE, TAB, VAR_SEP, OPEN_LIST, CLOSE_LIST, SPACE, LAMBDA, COMP_OPR,
-    PIPE, STMT_END,
+pub const SPLIT_TOKENS: [&'static str; 15] = [
+    OPEN_EXPR, CLOSE_EXPR, NEWLINE, TAB, VAR_SEP, OPEN_LIST, CLOSE_LIST, OPEN_MAP, CLOSE_MAP,
+    SPACE, LAMBDA, COMP_OPR, COMP_LEFT, PIPE, STMT_END,
 ];
 
+// binary operators rewritten into calls by the parser, loosest first: >> then $ then @
+pub const OPERATORS: [&'static str; 3] = [PIPE, COMP_OPR, COMP_LEFT];
+
 pub const DONT_ADD: [&'static str; 5] = [NEWLINE, TAB, VAR_SEP, SPACE, EMPTY];
 
-pub const OPEN_TOKENS: [&'static str; 2] = [OPEN_EXPR, OPEN_LIST];
-pub const CLOSE_TOKENS: [&'static str; 2] = [CLOSE_EXPR, CLOSE_LIST];
+pub const OPEN_TOKENS: [&'static str; 3] = [OPEN_EXPR, OPEN_LIST, OPEN_MAP];
+pub const CLOSE_TOKENS: [&'static str; 3] = [CLOSE_EXPR, CLOSE_LIST, CLOSE_MAP];
+// brackets inside each other: parsing recurses once per level
+pub const MAX_NESTING: usize = 256;
 
 pub const EXPR_TUP: (&'static str, &'static str) = (OPEN_EXPR, CLOSE_EXPR);
 pub const LIST_TUP: (&'static str, &'static str) = (OPEN_LIST, CLOSE_LIST);
+pub const MAP_TUP: (&'static str, &'static str) = (OPEN_MAP, CLOSE_MAP);
 
 // ASTNode types
 pub const EXPRESSION: &str = "expression";
 pub const LIST: &str = "list";
+pub const MAP: &str = "map";
 pub const SYMBOL: &str = "symbol";
 pub const NUMBER: &str = "number";
 pub const STRING: &str = "string";
@@ -95,16 +171,19 @@ pub const CMD_PREFIX: &str = ":";
 
 pub const COMMENT: &str = "#";
 
-pub const RESERVED_KEYWORDS: [&'static str; 36] = [
+pub const RESERVED_KEYWORDS: [&'static str; 41] = [
     LET_NAME,
     FN_NAME,
     IF_NAME,
+    TRY_NAME,
+    CATCH_NAME,
+    DATA_NAME,
+    MATCH_NAME,
     EQUALS,
     PUTS,
     PRINT,
     OR,
     AND,
-    IMPORT,
     CHAIN,
     CONS,
     SET,
@@ -117,6 +196,8 @@ pub const RESERVED_KEYWORDS: [&'static str; 36] = [
     CLOSE_EXPR,
     OPEN_LIST,
     CLOSE_LIST,
+    OPEN_MAP,
+    CLOSE_MAP,
     TRUE,
     FALSE,
     AND,
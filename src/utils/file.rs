@@ -11,7 +11,7 @@ use std::path::{Path, PathBuf};
 use std::ptr::read;
 
 use crate::constants::*;
-use crate::evaluate_all;
+use crate::evaluate_lexed;
 use crate::evaluator::context_tco::EvalContext;
 use crate::lex;
 use crate::lexer::*;
@@ -23,68 +23,6 @@ use crate::parser::parser::parse_all;
 pub const STL_FILE: &str = "~/rust/nova/stl.txt";
 pub const USER_FILE: &str = "~/rust/nova/user.txt";
 
-// convert to chars, insert ; everytime brackets goes to 0 (excluding first)
-// comments: ignore #
-pub fn separate_expressions(file_string: &str) -> Result<String> {
-    let mut all_chars: Vec<String> = vec![];
-    let mut stack: Vec<String> = vec![];
-    let mut line = 1;
-
-    let mut comment_stack: Vec<bool> = vec![];
-
-    for (idx, char) in file_string.chars().enumerate() {
-        let char_string = char.to_string();
-
-        // same character so we cant nest comments e.g # # internal # # - invalid
-        // this is why backlash is needed to escape in strings
-        if char_string.eq(COMMENT) {
-            if comment_stack.is_empty() {
-                comment_stack.push(true);
-            } else {
-                comment_stack.pop();
-            }
-            continue;
-        }
-
-        // comment: don't read
-        if !comment_stack.is_empty() {
-            continue;
-        }
-
-        if char_string.eq(STMT_END) && all_chars.last().unwrap().eq(STMT_END) {
-            continue;
-        }
-
-        all_chars.push(char_string.clone());
-
-        if char_string.eq("\n") {
-            line += 1;
-            continue;
-        }
-
-        if char_string.eq(OPEN_EXPR) {
-            stack.push(char_string);
-            continue;
-        }
-
-        if char_string.eq(CLOSE_EXPR) {
-            if stack.is_empty() {
-                let msg = format!("Unbalanced expression at line:{}, index:{}", line, idx);
-                return err!(msg);
-            }
-            stack.pop();
-
-            if stack.is_empty() {
-                all_chars.push(STMT_END.to_string());
-            }
-        }
-    }
-
-    let joined = all_chars.join("");
-
-    Ok(joined)
-}
-
 // partially written by ChatGPT
 pub fn extract_fndef(input: String) -> Result<String> {
     // Find the position of the arrow "=>"
@@ -132,7 +70,7 @@ pub fn save_file(filename: &str, ctx: EvalContext) -> std::result::Result<(), io
     let mut file = File::create(full_path)?;
 
     let mut count = 0;
-    for (key, value) in ctx.read().symbol_map.iter() {
+    for (key, value) in ctx.read().bindings().iter() {
         if BUILTINS.contains(&key.as_str()) {
             continue;
         }
@@ -153,8 +91,9 @@ pub fn import_file(filename: &str, ctx: &mut EvalContext) -> Result<()> {
     let file = read_file(filename)?;
     println!("Importing file: {}\n", filename);
 
-    let sep = separate_expressions(&file)?;
-    let results = evaluate_all(&sep, ctx)?;
+    // lexer adds the ; between expressions and keeps the file name for errors
+    let lexed = Lexer::new_file(file, filename)?;
+    let results = evaluate_lexed(lexed, ctx)?;
 
     for res in results {
         // println!("Res type in import:{}", res.result_type.to_string());
@@ -168,6 +107,13 @@ pub fn import_file(filename: &str, ctx: &mut EvalContext) -> Result<()> {
     Ok(())
 }
 
+fn file_error(filename: &str) -> Ex {
+    let msg = format!("File '{}' doesn't exist.", filename);
+    Ex::new(&msg).with_kind(ErrorKind::IO {
+        path: filename.to_string(),
+    })
+}
+
 // get file contents as string
 pub fn read_file(filename: &str) -> Result<String> {
     let file_path = get_full_path(filename);
@@ -176,7 +122,7 @@ pub fn read_file(filename: &str) -> Result<String> {
 
     match read {
         Ok(file_string) => Ok(file_string),
-        Err(_) => errf!("File '{}' doesn't exist.", filename),
+        Err(_) => Err(file_error(filename)),
     }
 }
 
@@ -186,6 +132,6 @@ fn read_lines(filename: &str) -> Result<io::Lines<io::BufReader<File>>> {
 
     match file_open {
         Ok(file) => Ok(io::BufReader::new(file).lines()),
-        Err(_) => errf!("File '{}' doesn't exist.", filename),
+        Err(_) => Err(file_error(filename)),
     }
 }
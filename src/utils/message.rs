@@ -1,5 +1,7 @@
 // Ex for Exception
 
+use crate::utils::span::Span;
+
 // add macro to create error
 #[macro_export]
 macro_rules! err {
@@ -11,30 +13,209 @@ macro_rules! err {
 #[macro_export]
 macro_rules! errf {
     ($msg:expr, $( $var:expr ),*) => {
-        Err(Ex::new(format!($msg, $($var)*).as_str()))
+        Err(Ex::new(format!($msg, $($var),*).as_str()))
     };
 }
 
 pub use err;
 pub use errf;
 
+// what went wrong: lets callers match on errors instead of comparing messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    Parse,
+    Name {
+        name: String,
+    }, // unbound identifier
+    Type {
+        expected: String,
+        actual: String,
+    }, // type names e.g "number"
+    Arity {
+        name: String,
+        expected: usize,
+        received: usize,
+    },
+    IO {
+        path: String,
+    },
+    User, // raised by the program
+    Limit {
+        limit: String,
+    }, // steps, time or depth budget ran out: can't be caught
+    Interrupt, // Ctrl-C in the REPL: can't be caught either
+    ZeroDivision, // div or mod by 0
+    Key {
+        key: String,
+    }, // get on a map without that key
+    Match, // no pattern in a match fits the value
+    Eval, // any other runtime error
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Parse => "ParseError",
+            ErrorKind::Name { .. } => "NameError",
+            ErrorKind::Type { .. } => "TypeError",
+            ErrorKind::Arity { .. } => "ArityError",
+            ErrorKind::IO { .. } => "IOError",
+            ErrorKind::User => "UserError",
+            ErrorKind::Limit { .. } => "LimitError",
+            ErrorKind::Interrupt => "Interrupted",
+            ErrorKind::ZeroDivision => "ZeroDivisionError",
+            ErrorKind::Key { .. } => "KeyError",
+            ErrorKind::Match => "MatchError",
+            ErrorKind::Eval => "EvalError",
+        }
+    }
+}
+
+// a call that was running when an error happened
+#[derive(Debug, Clone)]
+pub struct TraceFrame {
+    pub name: String, // function being called
+    pub call: String, // text of the call expression
+    pub span: Option<Span>,
+    pub repeated: usize, // identical frames after this one, e.g from recursion
+}
+
+impl TraceFrame {
+    pub fn new(name: &str, call: &str, span: Option<Span>) -> TraceFrame {
+        TraceFrame {
+            name: name.to_string(),
+            call: call.to_string(),
+            span,
+            repeated: 0,
+        }
+    }
+
+    fn same_call(&self, other: &TraceFrame) -> bool {
+        let location = |frame: &TraceFrame| frame.span.as_ref().map(|span| span.location());
+        self.name == other.name && self.call == other.call && location(self) == location(other)
+    }
+
+    //   in f: (f (pred n)) at file.txt:3:5
+    pub fn to_string(&self) -> String {
+        let mut call = self.call.clone();
+        if call.chars().count() > MAX_CALL_LEN {
+            call = call.chars().take(MAX_CALL_LEN).collect();
+            call.push_str("...");
+        }
+
+        let mut line = format!("  in {}: {}", self.name, call);
+        if let Some(span) = &self.span {
+            line.push_str(&format!(" at {}", span.location()));
+        }
+
+        if self.repeated > 0 {
+            line.push_str(&format!("\n  ... repeated {} more times", self.repeated));
+        }
+        line
+    }
+}
+
+// longer calls are cut off in traces
+const MAX_CALL_LEN: usize = 60;
+// after this many (collapsed) frames the rest of the trace is summarised
+const MAX_TRACE_FRAMES: usize = 20;
+
+// boxed so Result<T, Ex> stays small on the happy path
+#[derive(Debug)]
+pub struct Ex(Box<ExInner>);
+
 #[derive(Debug)]
-pub struct Ex {
+struct ExInner {
     message: String,
-    // type: Parse,Eval...
+    kind: ErrorKind,
+    span: Option<Span>,     // where in the source the error happened, if known
+    trace: Vec<TraceFrame>, // innermost call first
 }
 
 impl Ex {
     pub fn format_error(&self) -> String {
-        format!("Error: {}", self.message)
+        format!("Error: {}", self.0.message)
+    }
+
+    // message followed by the offending source line with a caret, then the calls
+    pub fn format_error_with_source(&self) -> String {
+        let mut error = self.format_error();
+        if let Some(span) = &self.0.span {
+            error = format!("{}\n{}", error, span.render());
+        }
+
+        if self.0.trace.is_empty() {
+            return error;
+        }
+
+        let mut lines = vec![error, String::from("Traceback (most recent call first):")];
+        lines.extend(
+            self.0
+                .trace
+                .iter()
+                .take(MAX_TRACE_FRAMES)
+                .map(|f| f.to_string()),
+        );
+
+        if self.0.trace.len() > MAX_TRACE_FRAMES {
+            let more = self.0.trace.len() - MAX_TRACE_FRAMES;
+            lines.push(format!("  ... and {} more calls", more));
+        }
+
+        lines.join("\n")
     }
 }
 
 impl Ex {
+    // kind defaults to Eval, use with_kind to be more specific
     pub fn new(msg: &str) -> Ex {
-        Ex {
+        Ex(Box::new(ExInner {
             message: msg.to_string(),
+            kind: ErrorKind::Eval,
+            span: None,
+            trace: vec![],
+        }))
+    }
+
+    pub fn get_trace(&self) -> &Vec<TraceFrame> {
+        &self.0.trace
+    }
+
+    // frames are added from the innermost call outwards
+    // the same call repeated back to back is only kept once
+    pub fn add_frame(&mut self, frame: TraceFrame) {
+        if let Some(last) = self.0.trace.last_mut() {
+            if last.same_call(&frame) {
+                last.repeated += 1;
+                return;
+            }
         }
+        self.0.trace.push(frame);
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> Ex {
+        self.0.kind = kind;
+        self
+    }
+
+    pub fn get_kind(&self) -> &ErrorKind {
+        &self.0.kind
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.0.message
+    }
+
+    pub fn get_span(&self) -> Option<&Span> {
+        self.0.span.as_ref()
+    }
+
+    // keep the innermost span: only set if there isn't one already
+    pub fn with_span(mut self, span: Option<Span>) -> Ex {
+        if self.0.span.is_none() {
+            self.0.span = span;
+        }
+        self
     }
 }
 
@@ -45,3 +226,69 @@ fn nova_error_test_new() {
     let ne = Ex::new("Some error");
     assert_eq!(ne.format_error(), "Error: Some error");
 }
+
+#[test]
+fn nova_error_test_kind() {
+    let ne = Ex::new("Some error");
+    assert_eq!(ne.get_kind(), &ErrorKind::Eval);
+
+    let kind = ErrorKind::Arity {
+        name: String::from("succ"),
+        expected: 1,
+        received: 2,
+    };
+    let ne = Ex::new("'succ' expected 1 arguments but received 2.").with_kind(kind.clone());
+
+    assert_eq!(ne.get_kind(), &kind);
+    assert_eq!(ne.get_kind().name(), "ArityError");
+    assert_eq!(
+        ne.get_message(),
+        "'succ' expected 1 arguments but received 2."
+    );
+    assert_eq!(
+        ne.format_error(),
+        "Error: 'succ' expected 1 arguments but received 2."
+    );
+}
+
+#[test]
+fn nova_error_test_trace() {
+    let mut ne = Ex::new("Expected a number but got 'x'");
+    ne.add_frame(TraceFrame::new("add", "(add n x)", None));
+    for _ in 0..3 {
+        ne.add_frame(TraceFrame::new("add", "(add n (f (pred n)))", None));
+    }
+    ne.add_frame(TraceFrame::new("f", "(f 3)", None));
+
+    assert_eq!(ne.get_trace().len(), 3);
+    assert_eq!(ne.format_error(), "Error: Expected a number but got 'x'");
+    assert_eq!(
+        ne.format_error_with_source(),
+        "Error: Expected a number but got 'x'\n\
+        Traceback (most recent call first):\n\
+        \x20 in add: (add n x)\n\
+        \x20 in add: (add n (f (pred n)))\n\
+        \x20 ... repeated 2 more times\n\
+        \x20 in f: (f 3)"
+    );
+}
+
+#[test]
+fn nova_error_test_span() {
+    use crate::utils::span::Source;
+    use std::rc::Rc;
+
+    let source = Rc::new(Source::new(None, "(add 1 x)"));
+    let inner = Some(Span::new(&source, 1, 8, 1));
+    let outer = Some(Span::new(&source, 1, 1, 1));
+
+    let ne = Ex::new("Unrecognised symbol: \"x\"")
+        .with_span(inner)
+        .with_span(outer);
+
+    assert_eq!(ne.get_span().unwrap().column, 8);
+    assert_eq!(
+        ne.format_error_with_source(),
+        "Error: Unrecognised symbol: \"x\"\n --> 1:8\n  |\n1 | (add 1 x)\n  |        ^"
+    );
+}
@@ -1,4 +1,5 @@
 pub mod constants;
 pub mod file;
 pub mod message;
+pub mod span;
 pub mod time;
@@ -0,0 +1,109 @@
+use std::fmt::{Debug, Display};
+use std::rc::Rc;
+
+// text that was lexed, shared by every span pointing into it
+pub struct Source {
+    pub name: Option<String>, // file name, None for REPL input
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(name: Option<&str>, text: &str) -> Source {
+        Source {
+            name: name.map(|x| x.to_string()),
+            text: text.to_string(),
+        }
+    }
+
+    // 1-indexed
+    pub fn get_line(&self, line: usize) -> Option<&str> {
+        self.text.lines().nth(line.checked_sub(1)?)
+    }
+}
+
+// position of a token: line and column are 1-indexed, length in chars
+#[derive(Clone)]
+pub struct Span {
+    pub source: Rc<Source>,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Span {
+    pub fn new(source: &Rc<Source>, line: usize, column: usize, length: usize) -> Span {
+        Span {
+            source: Rc::clone(source),
+            line,
+            column,
+            length,
+        }
+    }
+
+    // file:line:col
+    pub fn location(&self) -> String {
+        match &self.source.name {
+            Some(name) => format!("{}:{}:{}", name, self.line, self.column),
+            None => format!("{}:{}", self.line, self.column),
+        }
+    }
+
+    // the source line with a caret under the span
+    //  --> file.txt:3:5
+    //   |
+    // 3 |     (foo 1)
+    //   |      ^^^
+    pub fn render(&self) -> String {
+        let location = format!(" --> {}", self.location());
+
+        let line = match self.source.get_line(self.line) {
+            Some(line) => line,
+            None => return location,
+        };
+
+        let line_no = self.line.to_string();
+        let pad = " ".repeat(line_no.len());
+
+        // keep tabs so the caret lines up with the source
+        let prefix: String = line
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let caret = "^".repeat(self.length.max(1));
+
+        format!(
+            "{}\n{} |\n{} | {}\n{} | {}{}",
+            location, pad, line_no, line, pad, prefix, caret
+        )
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.location())
+    }
+}
+
+// don't dump the whole source when debug printing nodes
+impl Debug for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Span({})", self.location())
+    }
+}
+
+#[test]
+fn span_test_render() {
+    let source = Rc::new(Source::new(Some("test.txt"), "(def f (x) x)\n\t(foo 1)\n"));
+    let span = Span::new(&source, 2, 3, 3);
+
+    assert_eq!(span.location(), "test.txt:2:3");
+    assert_eq!(
+        span.render(),
+        " --> test.txt:2:3\n  |\n2 | \t(foo 1)\n  | \t ^^^"
+    );
+
+    let repl = Rc::new(Source::new(None, "(add 1 x)"));
+    let span = Span::new(&repl, 1, 8, 1);
+    assert_eq!(span.render(), " --> 1:8\n  |\n1 | (add 1 x)\n  |        ^");
+}
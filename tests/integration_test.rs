@@ -1,5 +1,5 @@
 #![recursion_limit = "5000"]
-use nova::{evaluate_all, evaluate_input_tco, evaluator::context_tco::EvalContext};
+use nova::{evaluate_all, evaluate_input_tco, evaluator::context_tco::EvalContext, ErrorKind};
 // fn compare(inp: &str, expected: &str, ctx: &mut EvalContext) {
 //     let res = evaluate_input_tco(inp.trim(), ctx);
 //     assert_eq!(res, expected.trim());
@@ -44,13 +44,472 @@ fn calc_test() {
         "21",
         "70",
         "18",
-        "1",
+        "Error: Expected a number but got 'true'",
         "Error: Expected a number but got '<function 'sub'>'",
     ];
 
     compare_many(exprs, expected, &mut ctx);
 }
 
+#[test]
+fn bigint_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def fac (n) (if (eq n 0) 1 (mul n (fac (pred n)))))",
+        "(def fib (n a b) (if (eq n 0) a (fib (pred n) b (add a b))))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(fac 30)",
+        "(fib 100 0 1)",
+        "(add 9223372036854775807 1)",
+        "(sub -9223372036854775808 1)",
+        "(mul 4294967296 4294967296)",
+        // back to a small number
+        "(sub (add 9223372036854775807 10) 10)",
+        "(eq (fac 30) (mul 30 (fac 29)))",
+        "(eq (add 9223372036854775807 1) 9223372036854775808)",
+        "(lt (fac 25) (fac 26))",
+        "(gt (fac 25) 5)",
+        "(div (fac 30) (fac 28))",
+        "(mod (fac 25) 1000007)",
+        "(div -9223372036854775808 -1)",
+        "(to-number \"18446744073709551616\")",
+        "(succ 9223372036854775807)",
+        "(idx [1 2] (fac 30))",
+    ];
+
+    let expected = vec![
+        "265252859812191058636308480000000",
+        "354224848179261915075",
+        "9223372036854775808",
+        "-9223372036854775809",
+        "18446744073709551616",
+        "9223372036854775807",
+        "true",
+        "true",
+        "true",
+        "true",
+        "870",
+        "913534",
+        "9223372036854775808",
+        "18446744073709551616",
+        "9223372036854775808",
+        "Error: 265252859812191058636308480000000 is too large, expected a number that fits in 64 bits.",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn float_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "3.14",
+        "1e-3",
+        "(add 1 2.5)",
+        "(mul 2.0 3)",
+        "(sub 0.5 1)",
+        "(div 7 2.0)",
+        "(div 7 2)",
+        "(mod -7.5 2)",
+        "(lt 1 1.5)",
+        "(ge 2.0 2)",
+        "(eq 1 1.0)",
+        "(lt 0.5 (mul 4294967296 4294967296))",
+        "(floor -2.5)",
+        "(ceil 2.1)",
+        "(round 2.5)",
+        "(to-int -2.7)",
+        "(to-int 1e20)",
+        "(to-float 3)",
+        "(to-number \"2.25\")",
+        "(div 1.5 0)",
+        "(idx [1 2] 1.0)",
+        "(eq (add (pow 2 60) 1) (to-float (pow 2 60)))",
+        "(eq (pow 2 60) (to-float (pow 2 60)))",
+        "(lt (to-float (pow 2 60)) (add (pow 2 60) 1))",
+        "(eq 1/3 (div 1.0 3))",
+    ];
+
+    let expected = vec![
+        "3.14",
+        "0.001",
+        "3.5",
+        "6.0",
+        "-0.5",
+        "3.5",
+        "7/2",
+        "0.5",
+        "true",
+        "true",
+        "true",
+        "true",
+        "-3",
+        "3",
+        "3",
+        "-2",
+        "100000000000000000000",
+        "3.0",
+        "2.25",
+        "Error: 'div' can't divide 1.5 by zero.",
+        "Error: Expected an integer but got '1.0'",
+        "false",
+        "true",
+        "true",
+        "false",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn rational_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "1/3",
+        "-2/4",
+        "4/2",
+        "(add 1/3 1/6)",
+        "(sub 1/2 1)",
+        "(mul 2/3 3/2)",
+        "(div 1 3)",
+        "(div (fac 3) 4)",
+        "(add 1/2 0.25)",
+        "(lt 1/3 1/2)",
+        "(gt 1/3 0.3)",
+        "(eq 2/4 1/2)",
+        "(eq 1/2 0.5)",
+        "(mod 7/2 1)",
+        "(floor -7/2)",
+        "(ceil -7/2)",
+        "(round 5/2)",
+        "(to-int -7/2)",
+        "(to-float 1/8)",
+        "(to-number \"3/9\")",
+        "(div 1/2 0)",
+    ];
+
+    let expected = vec![
+        "1/3",
+        "-1/2",
+        "2",
+        "1/2",
+        "-1/2",
+        "1",
+        "1/3",
+        "3/2",
+        "0.75",
+        "true",
+        "true",
+        "true",
+        "true",
+        "1/2",
+        "-4",
+        "-3",
+        "3",
+        "-3",
+        "0.125",
+        "1/3",
+        "Error: 'div' can't divide 1/2 by zero.",
+    ];
+
+    evaluate_all(
+        "(def fac (n) (if (eq n 0) 1 (mul n (fac (pred n)))))",
+        &mut ctx,
+    )
+    .unwrap();
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn math_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "(abs -5)",
+        "(abs -1/2)",
+        "(min 3 1 2)",
+        "(max 3 1.5 7/2)",
+        "((max 3) 4)",
+        "(pow 2 100)",
+        "(pow 2 -2)",
+        "(pow 4 0.5)",
+        "(sqrt 16)",
+        "(gcd 12 18 -8)",
+        "(lcm 4 6 10)",
+        "(sign -3.5)",
+        "(even? 4)",
+        "(odd? 4)",
+        "(sin 0)",
+        "(log 1)",
+        "(band 12 10)",
+        "(bor 12 10 1)",
+        "(bxor 12 10)",
+        "(shl 1 70)",
+        "(shr -5 1)",
+        "(sqrt -1)",
+        "(pow 0 -1)",
+        "(gcd 1/2 4)",
+        "(pow 2 1000000000)",
+        "(pow 1/2 -1000000000)",
+        "(pow -1 1000000000)",
+        "(shl 1 100000000000)",
+        "(shl -3 33)",
+        "(shr (shl -3 100) 98)",
+        "(band (shl 1 100) -1)",
+        "(bxor (shl 1 64) 1 -1)",
+        "(band -12 10)",
+        "(pow -8 1/3)",
+    ];
+
+    let expected = vec![
+        "5",
+        "1/2",
+        "1",
+        "7/2",
+        "4",
+        "1267650600228229401496703205376",
+        "1/4",
+        "2.0",
+        "4.0",
+        "2",
+        "60",
+        "-1",
+        "true",
+        "false",
+        "0.0",
+        "0.0",
+        "8",
+        "15",
+        "6",
+        "1180591620717411303424",
+        "-3",
+        "Error: 'sqrt' is undefined for -1.",
+        "Error: 'pow' can't raise 0 to a negative power.",
+        "Error: 'gcd' expected an integer but got '1/2'",
+        "Error: 'pow' can't give a number of more than 1048576 bits.",
+        "Error: 'pow' can't give a number of more than 1048576 bits.",
+        "1",
+        "Error: 'shl' can't give a number of more than 1048576 bits.",
+        "-25769803776",
+        "-12",
+        "1267650600228229401496703205376",
+        "-18446744073709551618",
+        "0",
+        "Error: 'pow' is undefined for -8 and 1/3.",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn comparison_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "(lt 1 2)",
+        "(gt 1 2)",
+        "(le 2 2)",
+        "(ge 1 2)",
+        "(neq 1 2)",
+        "(lt \"abc\" \"abd\")",
+        "(not (eq 1 1))",
+        "(not [])",
+        "(lt 1 true)",
+        "(filter_lt [5 1 4 2] 3)",
+    ];
+
+    evaluate_all(
+        "(def filter_lt (lst n) (if (eq lst []) [] (if (lt (car lst) n) (cons (car lst) (filter_lt (cdr lst) n)) (filter_lt (cdr lst) n))))",
+        &mut ctx,
+    )
+    .unwrap();
+
+    let expected = vec![
+        "true",
+        "false",
+        "true",
+        "false",
+        "true",
+        "true",
+        "false",
+        "true",
+        "Error: 'lt' can't compare 'true'",
+        "[1,2]",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn division_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "(div 7 2)",
+        "(div -7 2)",
+        "(mod 7 3)",
+        "(mod -7 3)",
+        "(mod 7 -3)",
+        "(div 1 0)",
+        "(mod 1 0)",
+        "(try (div 1 0) (catch e (err-kind e)))",
+    ];
+
+    let expected = vec![
+        "7/2",
+        "-7/2",
+        "1",
+        "2",
+        "-2",
+        "Error: 'div' can't divide 1 by zero.",
+        "Error: 'mod' can't divide 1 by zero.",
+        "ZeroDivisionError",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn boolean_test() {
+    let mut ctx = EvalContext::new();
+    evaluate_all(
+        "(def all_pos (lst) (or (eq lst []) (and (gt (car lst) 0) (all_pos (cdr lst)))))",
+        &mut ctx,
+    )
+    .unwrap();
+
+    let exprs = vec![
+        "(and true true)",
+        "(and true false)",
+        "(or false true)",
+        "(or false false)",
+        // short circuit: the error is never evaluated
+        "(and false (error \"boom\"))",
+        "(or true (error \"boom\"))",
+        "(and true 5)",
+        "(or 0 [] \"x\")",
+        "(and true (error \"boom\"))",
+        "(all_pos [1 2 3])",
+        "(all_pos [1 -2 3])",
+    ];
+
+    let expected = vec![
+        "true",
+        "false",
+        "true",
+        "false",
+        "false",
+        "true",
+        "5",
+        "x",
+        "Error: boom",
+        "true",
+        "false",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn get_set_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "(get [1 2 3] 1)",
+        "(set [1 2 3] 1 5)",
+        "(let lst [1 2 3] new (set lst 0 0) [lst new])",
+        "(get [1 2 3] 3)",
+        "(set [] 0 1)",
+    ];
+
+    let expected = vec![
+        "2",
+        "[1,5,3]",
+        "[[1,2,3],[0,2,3]]",
+        "Error: Index 3 is out of range for list of length 3.",
+        "Error: Index 0 is out of range for list of length 0.",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+fn map_test() {
+    let mut ctx = EvalContext::new();
+    evaluate_all("(def square_map (x) {x (mul x x)})", &mut ctx).unwrap();
+
+    let exprs = vec![
+        "{\"a\" 1 \"b\" (add 1 1)}",
+        "{}",
+        "(get {\"a\" 1, \"b\" 2} \"b\")",
+        "(set {\"a\" 1} \"b\" 2)",
+        "(let m {\"a\" 1} new (set m \"a\" 5) [m new])",
+        "(del {\"a\" 1 \"b\" 2} \"a\")",
+        "(del {\"a\" 1} \"z\")",
+        "(keys {\"a\" 1 \"b\" 2})",
+        "(values {\"a\" 1 \"b\" 2})",
+        "(has? {1 2} 1.0)",
+        "(has? {1 2} 2)",
+        "(eq {\"a\" 1 \"b\" 2} {\"b\" 2 \"a\" 1})",
+        "(eq {\"a\" 1} {\"a\" 2})",
+        "{\"a\" 1 \"a\" 2}",
+        "(get {[1 2] \"x\"} [1 2])",
+        "(length {1 2 3 4})",
+        "(square_map 3)",
+        "(try (get {} \"z\") (catch e (err-kind e)))",
+        "(get {\"a\" 1} \"z\")",
+        "{succ 1}",
+        "{1 2 3}",
+        "(keys [1 2])",
+    ];
+
+    let expected = vec![
+        "{\"a\" 1,\"b\" 2}",
+        "{}",
+        "2",
+        "{\"a\" 1,\"b\" 2}",
+        "[{\"a\" 1},{\"a\" 5}]",
+        "{\"b\" 2}",
+        "{\"a\" 1}",
+        "[\"a\",\"b\"]",
+        "[1,2]",
+        "true",
+        "false",
+        "true",
+        "false",
+        "{\"a\" 2}",
+        "x",
+        "2",
+        "{3 9}",
+        "KeyError",
+        "Error: Key 'z' is not in the map.",
+        "Error: 'map' can't use '<function 'succ'>' as a map key",
+        "Error: Map literal has a key without a value: '3'.",
+        "Error: 'keys' expected a map but got '[1,2]'",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+
+    // lookups don't scan: big literals and many sets stay fast
+    let pairs: Vec<String> = (0..20000).map(|i| format!("\"k{}\" {}", i, i)).collect();
+    let def = format!("let big {{{}}}", pairs.join(" "));
+    evaluate_all(&def, &mut ctx).expect("Should define map");
+    let def = "(def fill (m n) (if (eq n 0) m (fill (set m n (mul n n)) (pred n))))";
+    evaluate_all(def, &mut ctx).expect("Should define function");
+
+    let exprs = vec![
+        "(length big)",
+        "(get big \"k19999\")",
+        "(length (del big \"k5\"))",
+        "(get (fill {} 20000) 150)",
+        "(length (fill {1.0 0} 20000))",
+    ];
+    let expected = vec!["20000", "19999", "19999", "22500", "20000"];
+    compare_many(exprs, expected, &mut ctx);
+}
+
 #[test]
 fn if_test() {
     let mut ctx = EvalContext::new();
@@ -194,3 +653,899 @@ pub fn curry_test() {
 
 // // ((map fn) x) -> (map fn) res is fn call
 // // (g (map fn) x) -> (map fn) res is variable
+
+#[test]
+pub fn list_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "[]",
+        "[1,2,(add 5 6),[3,4,[5,6,(sub 4 5)]]]",
+        "[(add 1 2)]",
+        "(cons 1 [2 3])",
+        "(cons [1 2] [3])",
+        "(car [1 2 3])",
+        "(cdr [1 2 3])",
+        "(cdr [1])",
+        "(idx [1 2 3] 2)",
+        "(eq [1 [2 3]] [1 [2 3]])",
+        "(eq [1 2] [1 3])",
+        "(eq [] [])",
+        "(if [] 1 2)",
+        "(car [])",
+        "(idx [1 2] 5)",
+        "(cons 1 2)",
+    ];
+
+    let expected = vec![
+        "[]",
+        "[1,2,11,[3,4,[5,6,-1]]]",
+        "[3]",
+        "[1,2,3]",
+        "[[1,2],3]",
+        "1",
+        "[2,3]",
+        "[]",
+        "3",
+        "true",
+        "false",
+        "true",
+        "2",
+        "Error: 'car' received an empty list.",
+        "Error: Index 5 is out of range for list of length 2.",
+        "Error: Expected a list but got '2'",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn list_stl_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def len (lst) (if (eq lst []) 0 (succ (len (cdr lst)))))",
+        "(def sum (lst) (if (eq lst []) 0 (add (car lst) (sum (cdr lst)))))",
+        "(def map (fn lst) (if (eq lst []) [] (cons (fn (car lst)) (map fn (cdr lst)))))",
+        "(def fold (f acc lst) (if (eq lst []) acc (fold f (f acc (car lst)) (cdr lst))))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    compare("(len [1 2 3 4])", "4", &mut ctx);
+    compare("(sum [1 2 3 4])", "10", &mut ctx);
+    compare("(map succ [1 2 3])", "[2,3,4]", &mut ctx);
+    compare("(map (add 10) [])", "[]", &mut ctx);
+    compare("(map cdr [[1 2] [3]])", "[[2],[]]", &mut ctx);
+    compare("(fold add 0 [1 2 3 4 5])", "15", &mut ctx);
+    compare("(let lst [(len [1 2]) (sum [3 4])] lst)", "[2,7]", &mut ctx);
+}
+
+#[test]
+pub fn lazy_list_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def nats (n) (lcons n (nats (succ n))))",
+        "(def ltake (n lst) (if (eq n 0) [] (cons (lcar lst) (ltake (pred n) (lcdr lst)))))",
+        "(def lmap (f lst) (lcons (f (lcar lst)) (lmap f (lcdr lst))))",
+        "(def ldrop (n lst) (if (eq n 0) lst (ldrop (pred n) (lcdr lst))))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    compare("(lcar (nats 1))", "1", &mut ctx);
+    compare("(lcar (lcdr (lcdr (nats 1))))", "3", &mut ctx);
+    compare("(ltake 5 (nats 0))", "[0,1,2,3,4]", &mut ctx);
+    compare("(ltake 3 (lmap (mul 2) (nats 1)))", "[2,4,6]", &mut ctx);
+    compare("(lcar (ldrop 2000 (nats 0)))", "2000", &mut ctx);
+
+    // tail is memoized after the first lcdr
+    compare("let s (nats 1)", "[1,...]", &mut ctx);
+    compare("(lcar (lcdr s))", "2", &mut ctx);
+    compare("s", "[1,2,...]", &mut ctx);
+    compare("(eq s s)", "true", &mut ctx);
+    compare(
+        "(lcdr [])",
+        "Error: 'lcdr' received an empty list.",
+        &mut ctx,
+    );
+    compare(
+        "(lcar [])",
+        "Error: 'lcar' received an empty list.",
+        &mut ctx,
+    );
+    compare("(lcar 5)", "Error: Expected a list but got '5'", &mut ctx);
+
+    // the whole forced stream is dropped when the let ends
+    let walk = "(def walk (n s) (if (eq n 0) (lcar s) (walk (pred n) (lcdr s))))";
+    evaluate_all(walk, &mut ctx).expect("Should define function");
+    compare("(let s (nats 0) (walk 300000 s))", "300000", &mut ctx);
+
+    // the head is evaluated on the stacks, so non-tail recursion through it is fine
+    let lh = "(def lh (n) (if (eq n 0) 0 (lcar (lcons (succ (lh (pred n))) []))))";
+    evaluate_all(lh, &mut ctx).expect("Should define function");
+    compare("(lh 20000)", "20000", &mut ctx);
+}
+
+#[test]
+pub fn string_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "\"a b\"",
+        "(concat \"a (b\" \" c]\" \"#d\")",
+        "(concat \"say \\\"hi\\\"\" \"!\")",
+        "(length \"hello\")",
+        "(length [1 2 3])",
+        "(substring \"hello\" 1 3)",
+        "(split \"a,b,,c\" \",\")",
+        "(join [\"a\" 1 true] \"-\")",
+        "(add (to-number \"40\") 2)",
+        "(concat (to-string 42) (to-string [1 2]))",
+        "(eq \"abc\" (concat \"a\" \"bc\"))",
+        "(eq \"abc\" \"abd\")",
+        "(if \"\" 1 2)",
+        "(to-number \"4x\")",
+        "(substring \"hi\" 1 5)",
+        "(concat \"a\" 1)",
+        "[\"1\" 1 \"say \\\"hi\\\"\" [\"\"]]",
+    ];
+
+    let expected = vec![
+        "a b",
+        "a (b c]#d",
+        "say \"hi\"!",
+        "5",
+        "3",
+        "el",
+        "[\"a\",\"b\",\"\",\"c\"]",
+        "a-1-true",
+        "42",
+        "42[1,2]",
+        "true",
+        "false",
+        "2",
+        "Error: Can't convert '4x' to a number.",
+        "Error: Substring range 1..5 is out of range for string of length 2.",
+        "Error: Expected a string but got '1'",
+        "[\"1\",1,\"say \\\"hi\\\"\",[\"\"]]",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn string_file_test() {
+    use nova::evaluate_lexed;
+    use nova::lexer::Lexer;
+
+    let file = "(def greet (name) (concat \"hi (\" name \") #1\"))\n# comment #\n(greet \"bob\")";
+    let lexed = Lexer::new_file(file.to_string(), "test.nv").expect("Should lex");
+
+    let mut ctx = EvalContext::new();
+    let res = evaluate_lexed(lexed, &mut ctx).expect("Should evaluate");
+    assert_eq!(res.get(1).unwrap().result, "hi (bob) #1");
+
+    // top level literals spanning lines stay whole
+    let file = "[1 2\n (add 1 2)]\n{\"k\" (succ 1)\n}\n(add 1 1)";
+    let lexed = Lexer::new_file(file.to_string(), "test.nv").expect("Should lex");
+    let res = evaluate_lexed(lexed, &mut ctx).expect("Should evaluate");
+    let results: Vec<&str> = res.iter().map(|r| r.result.as_str()).collect();
+    assert_eq!(results, vec!["[1,2,3]", "{\"k\" 2}", "2"]);
+}
+
+#[test]
+pub fn lambda_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def map (fn lst) (if (eq lst []) [] (cons (fn (car lst)) (map fn (cdr lst)))))",
+        "(def fold (f acc lst) (if (eq lst []) acc (fold f (f acc (car lst)) (cdr lst))))",
+        "(def make_adder (n) (x -> (add x n)))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(x -> (add x 1))",
+        "((x -> (add x 1)) 1)",
+        "(x->y->(add x y)) (1) (2)",
+        "((x y -> (add x y)) 10)",
+        "(map (x -> (mul x x)) [1 2 3])",
+        "(fold (acc x -> (add acc x)) 0 [1 2 3 4])",
+        "(let k 10 (map (x -> add x k) [1 2]))",
+        "(let add5 (make_adder 5) (add5 10))",
+        "let inc (x -> (succ x))",
+        "(inc 41)",
+        "((x -> x) 1 2)",
+    ];
+
+    let expected = vec![
+        "lambda(x) => (add x 1)",
+        "2",
+        "3",
+        "lambda(y) => (add x y)",
+        "[1,4,9]",
+        "10",
+        "[11,12]",
+        "15",
+        "lambda(x) => (succ x)",
+        "42",
+        "Error: 'lambda' expected 1 arguments but received 2.",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn operator_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def map (fn lst) (if (eq lst []) [] (cons (fn (car lst)) (map fn (cdr lst)))))",
+        "(def for_each (f lst) (if (eq lst []) [] (> (f (car lst)) (for_each f (cdr lst)))))",
+        "(def nil? (lst) (eq lst []))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "5 >> succ",
+        "3 >> sub 10",
+        "[1 2 3] >> map (mul 2) >> map succ",
+        "succ $ mul 2 $ add 1 2",
+        "(if (eq true $ nil? []) 1 0)",
+        "((succ @ mul 2) 5)",
+        "(map (succ @ succ) [1 2])",
+        "map (mul 3) $ [1 2] >> cons 0",
+        "[1 2 3] >> for_each $ puts",
+        "[1 2 3] >> map $ mul 2",
+        "2 >> add $ mul 3 4",
+    ];
+
+    let expected = vec![
+        "6", "7", "[3,5,7]", "7", "1", "11", "[3,4]", "[0,3,6]", "", "[2,4,6]", "14",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn fn_body_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def f (x) (let y (add x 1)) (let z (mul y 2)) (add y z))",
+        "(def g (x) (puts x) (def h (y) (mul y x)) (h 3))",
+        "(def loop (n acc) (let m (pred n)) (if (eq n 0) acc (loop m (add acc n))))",
+        "(def shadow (x) (let x (succ x)) x)",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(f 1)",
+        "(g 5)",
+        "(loop 5000 0)",
+        "(shadow 1)",
+        "(let y 100 (f 1))",
+    ];
+    let expected = vec!["6", "15", "12502500", "2", "6"];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn span_test() {
+    let mut ctx = EvalContext::new();
+    let check = |inp: &str, location: &str, ctx: &mut EvalContext| {
+        let err = evaluate_all(inp, ctx).err().expect("Should be an error");
+        assert_eq!(err.get_span().unwrap().location(), location);
+    };
+
+    // symbol in the middle of a line
+    check("(add 1\n   (mul 2 xyz))", "2:11", &mut ctx);
+    // unterminated string
+    check("(concat \"ab", "1:9", &mut ctx);
+    // bracket errors point at the opening bracket
+    check("(add 1 [2 3)", "1:8", &mut ctx);
+    // a closing bracket that nothing opened
+    check("(add 1 2))", "1:10", &mut ctx);
+    check("(add 1\n  2)]", "2:5", &mut ctx);
+
+    // errors inside a function body point at the call inside the body
+    evaluate_all("(def f (x)\n  (car x))", &mut ctx).unwrap();
+    check("(f [])", "2:4", &mut ctx);
+
+    let err = evaluate_all("(add 1 xyz)", &mut ctx).err().unwrap();
+    assert_eq!(
+        err.format_error_with_source(),
+        "Error: Unrecognised symbol: \"xyz\"\n --> 1:8\n  |\n1 | (add 1 xyz)\n  |        ^^^\n\
+        Traceback (most recent call first):\n  in add: (add 1 xyz) at 1:2"
+    );
+}
+
+#[test]
+pub fn trace_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def recr (n) (if (eq n 0) (add 1 sub) (add n (recr (pred n)))))",
+        "(def g (x) (mul 2 (recr x)))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let trace = |inp: &str, ctx: &mut EvalContext| {
+        let err = evaluate_all(inp, ctx).err().expect("Should be an error");
+        let frames: Vec<(String, String, usize)> = err
+            .get_trace()
+            .iter()
+            .map(|f| (f.name.clone(), f.call.clone(), f.repeated))
+            .collect();
+        frames
+    };
+
+    let frame =
+        |name: &str, call: &str, repeated: usize| (name.to_string(), call.to_string(), repeated);
+
+    // recursion collapses into one frame
+    assert_eq!(
+        trace("(g 100)", &mut ctx),
+        vec![
+            frame("add", "(add 1 sub)", 0),
+            frame("add", "(add n (recr (pred n)))", 99),
+            frame("mul", "(mul 2 (recr x))", 0),
+        ]
+    );
+
+    // tail calls leave no frame: only the call waiting on the result of car
+    evaluate_all(
+        "(def loop (n) (if (eq n 0) (car []) (loop (pred n))))",
+        &mut ctx,
+    )
+    .unwrap();
+    assert_eq!(
+        trace("(succ (loop 1000))", &mut ctx),
+        vec![
+            frame("car", "(car [])", 0),
+            frame("succ", "(succ (loop 1000))", 0)
+        ]
+    );
+
+    let err = evaluate_all("(g 3)", &mut ctx).err().unwrap();
+    let expected = "  in add: (add n (recr (pred n))) at 1:41\n  \
+        ... repeated 2 more times\n  in mul: (mul 2 (recr x)) at 1:13";
+    assert!(err.format_error_with_source().ends_with(expected));
+}
+
+#[test]
+pub fn error_kind_test() {
+    let mut ctx = EvalContext::new();
+    let kind = |inp: &str, ctx: &mut EvalContext| {
+        let err = evaluate_all(inp, ctx).err().expect("Should be an error");
+        err.get_kind().clone()
+    };
+
+    assert_eq!(kind("(add 1", &mut ctx), ErrorKind::Parse);
+    assert_eq!(kind("(concat \"ab", &mut ctx), ErrorKind::Parse);
+    assert_eq!(
+        kind("(add 1 xyz)", &mut ctx),
+        ErrorKind::Name {
+            name: String::from("xyz")
+        }
+    );
+    assert_eq!(
+        kind("(add 1 \"a\")", &mut ctx),
+        ErrorKind::Type {
+            expected: String::from("number"),
+            actual: String::from("string")
+        }
+    );
+    assert_eq!(
+        kind("(car 5)", &mut ctx),
+        ErrorKind::Type {
+            expected: String::from("list"),
+            actual: String::from("number")
+        }
+    );
+    assert_eq!(
+        kind("(succ 1 2)", &mut ctx),
+        ErrorKind::Arity {
+            name: String::from("succ"),
+            expected: 1,
+            received: 2
+        }
+    );
+
+    evaluate_all("(def f (x y) (add x y))", &mut ctx).unwrap();
+    assert_eq!(
+        kind("(f 1 2 3)", &mut ctx),
+        ErrorKind::Arity {
+            name: String::from("f"),
+            expected: 2,
+            received: 3
+        }
+    );
+
+    let err = nova::import_file("no_such_file.nv", &mut ctx).unwrap_err();
+    assert_eq!(
+        err.get_kind(),
+        &ErrorKind::IO {
+            path: String::from("no_such_file.nv")
+        }
+    );
+    assert_eq!(
+        err.format_error(),
+        "Error: File 'no_such_file.nv' doesn't exist."
+    );
+}
+
+#[test]
+pub fn try_catch_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def deep (n) (if (eq n 0) (error \"bottom\") (add 1 (deep (pred n)))))",
+        "(def safe_car (lst) (try (car lst) (catch e 0)))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(try (add 1 2) (catch e 0))",
+        "(add 10 (try (car []) (catch e 5)))",
+        "(try (add 1 \"a\") (catch e (err-kind e)))",
+        "(try (error \"boom\") (catch e (err-msg e)))",
+        "(try (error \"boom\") (catch e e))",
+        "(try (deep 500) (catch e (concat \"caught \" (err-msg e))))",
+        "(add (safe_car []) (safe_car [4]) (safe_car [5]))",
+        "(try (try (error \"in\") (catch e (error (concat \"re:\" (err-msg e))))) (catch e (err-msg e)))",
+        "(try (try (succ 1 2) (catch e (error e))) (catch e (err-kind e)))",
+        "((try succ (catch e pred)) 5)",
+        "(try (car []) (catch e (car [])))",
+        "(try 1 (catch e 2)) >> succ",
+        "e",
+    ];
+
+    let expected = vec![
+        "3",
+        "15",
+        "TypeError",
+        "boom",
+        "UserError: boom",
+        "caught bottom",
+        "9",
+        "re:in",
+        "ArityError",
+        "6",
+        "Error: 'car' received an empty list.",
+        "2",
+        "Error: Unrecognised symbol: \"e\"",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn limits_test() {
+    use nova::evaluator::context_tco::Strategy;
+    use nova::evaluator::limits::EvalLimits;
+    use std::time::Duration;
+
+    let limit = |limits: EvalLimits, inp: &str| {
+        let mut ctx = EvalContext::new().with_limits(limits);
+        evaluate_all("(def loop (n) (loop (succ n)))", &mut ctx).unwrap();
+        evaluate_all(
+            "(def recr (n) (if (eq n 0) 0 (add n (recr (pred n)))))",
+            &mut ctx,
+        )
+        .unwrap();
+
+        match evaluate_all(inp, &mut ctx) {
+            Ok(res) => Ok(res[0].result.clone()),
+            Err(err) => Err(err.get_kind().clone()),
+        }
+    };
+
+    let steps = EvalLimits {
+        max_steps: Some(10000),
+        ..Default::default()
+    };
+    let limit_kind = |name: &str| ErrorKind::Limit {
+        limit: name.to_string(),
+    };
+
+    assert_eq!(limit(steps, "(recr 100)"), Ok(String::from("5050")));
+    assert_eq!(limit(steps, "(loop 0)"), Err(limit_kind("steps")));
+    // try can't catch running out
+    assert_eq!(
+        limit(steps, "(try (loop 0) (catch e 0))"),
+        Err(limit_kind("steps"))
+    );
+
+    let timeout = EvalLimits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+    assert_eq!(limit(timeout, "(loop 0)"), Err(limit_kind("time")));
+
+    let depth = EvalLimits {
+        max_depth: Some(100),
+        ..Default::default()
+    };
+    assert_eq!(limit(depth, "(recr 10)"), Ok(String::from("55")));
+    assert_eq!(limit(depth, "(recr 1000)"), Err(limit_kind("depth")));
+
+    let args = vec!["--max-steps", "100", "file.txt", "--timeout", "20"];
+    let args = args.into_iter().map(|x| x.to_string()).collect();
+    let (limits, strategy, rest) = nova::parse_flags(args).unwrap();
+
+    assert_eq!(limits.max_steps, Some(100));
+    assert_eq!(limits.timeout, Some(Duration::from_millis(20)));
+    assert_eq!(limits.max_depth, None);
+    assert_eq!(strategy, Strategy::Strict);
+    assert_eq!(rest, vec!["file.txt"]);
+
+    let args = vec!["--lazy", "file.txt", "--max-depth", "10"];
+    let args = args.into_iter().map(|x| x.to_string()).collect();
+    let (limits, strategy, rest) = nova::parse_flags(args).unwrap();
+
+    assert_eq!(limits.max_depth, Some(10));
+    assert_eq!(strategy, Strategy::Lazy);
+    assert_eq!(rest, vec!["file.txt"]);
+
+    let args = vec![String::from("--max-depth")];
+    assert!(nova::parse_flags(args).is_err());
+}
+
+// recursion through if conditions, operators, let, chain and body statements
+// only grows the evaluator's stacks, not the Rust stack
+#[test]
+pub fn deep_nesting_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def cond (n) (if (eq n 0) true (if (cond (pred n)) true false)))",
+        "(def oper (n) (if (eq n 0) (x -> x) ((if true (oper (pred n)) 0) (x -> x))))",
+        "(def bind (n) (if (eq n 0) 0 (let x (bind (pred n)) (succ x))))",
+        "(def seq (n) (if (eq n 0) 0 (> (seq (pred n)))))",
+        "(def stmt (n) (if (eq n 0) 0 (stmt (pred n))) n)",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(cond 5000)",
+        "((if true (oper 5000) 0) 7)",
+        "(bind 5000)",
+        "(seq 5000)",
+        "(stmt 5000)",
+    ];
+    let expected = vec!["true", "7", "5000", "", "5000"];
+
+    compare_many(exprs, expected, &mut ctx);
+
+    // deeply nested source: parsing and printing recurse once per level, so it is bounded
+    let nested = |depth| format!("{}0{}", "(add 1 ".repeat(depth), ")".repeat(depth));
+    compare(&nested(256), "256", &mut ctx);
+    compare(
+        &nested(257),
+        "Error: Can't nest more than 256 brackets.",
+        &mut ctx,
+    );
+}
+
+// and/or wait for their args on the evaluator's stacks too
+#[test]
+pub fn deep_short_circuit_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def d_and (n) (if (eq n 0) true (and (d_and (pred n)) true)))",
+        "(def d_or (n) (if (eq n 0) false (or (d_or (pred n)) (d_or 0) 1)))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(d_and 20000)",
+        "(d_or 20000)",
+        "(and 1 false (error \"boom\"))",
+    ];
+    let expected = vec!["true", "1", "false"];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+// every call shares the function body, so results are matched to frames by position:
+// the inner add waits on (f (pred n)), which reaches the same inner add again
+#[test]
+pub fn shared_body_test() {
+    let mut ctx = EvalContext::new();
+    let exprs = vec![
+        "(def f (n) (if (eq n 0) 0 (add 1 (add (f (pred n)) n))))",
+        "(f 3)",
+        "(def g (n) (if (eq n 0) [] (cons n (g (pred n)))))",
+        "(g 3)",
+    ];
+    let expected = vec![
+        "f(n) => (if (eq n 0) 0 (add 1 (add (f (pred n)) n)))",
+        "9",
+        "g(n) => (if (eq n 0) [] (cons n (g (pred n))))",
+        "[3,2,1]",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn scoping_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def make_adder (n) (x -> (add x n)))",
+        "(def get_x (unused) x)",
+        "(def call_get_x (x) (get_x 0))",
+        "(def use_adder (n) (let add1 (make_adder 1) (add1 n)))",
+        "(def uses_later (n) (later n))",
+        "(def later (n) (mul n 2))",
+        "(def outer (x) (def inner (x) (mul x 10)) (add (inner 2) x))",
+        "(def curried (a) (b -> (c -> (add a (add b c)))))",
+        "let y 100",
+        "(def shadow (y) (add y 1))",
+        "(def read_y (unused) y)",
+        "(def shadow_read_y (y) (read_y 0))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(call_get_x 5)",
+        "(use_adder 10)",
+        "(let n 50 add1 (make_adder 1) (add1 2))",
+        "(uses_later 4)",
+        "(outer 1)",
+        "(let f (curried 1) g (f 2) (g 3))",
+        "(let a 10 b 20 f (curried 1) g (f 2) (g 3))",
+        "(shadow 1)",
+        "(shadow_read_y 5)",
+        "(let y 7 (read_y 0))",
+        "(def later (n) (mul n 3))",
+        "(uses_later 4)",
+    ];
+
+    let expected = vec![
+        "Error: Unrecognised symbol: \"x\"",
+        "11",
+        "3",
+        "8",
+        "21",
+        "6",
+        "6",
+        "2",
+        "100",
+        "100",
+        "later(n) => (mul n 3)",
+        "12",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+}
+
+#[test]
+pub fn mutual_recursion_test() {
+    let mut ctx = EvalContext::new();
+    let defs = vec![
+        "(def is_even (n) (if (eq n 0) true (is_odd (pred n))))",
+        "(def is_odd (n) (if (eq n 0) false (is_even (pred n))))",
+        "(def uses_forward (n) (forward n))",
+        "(def forward (n) (succ n))",
+        "(def parity (n)
+            (def ev (k) (if (eq k 0) true (od (pred k))))
+            (def od (k) (if (eq k 0) false (ev (pred k))))
+            (ev n))",
+        "(def count_down (n)
+            (def ping (k acc) (if (eq k 0) acc (pong (pred k) (succ acc))))
+            (def pong (k acc) (if (eq k 0) acc (ping (pred k) acc)))
+            (ping n 0))",
+        "(def split_parity (n)
+            (def ev (k) (if (eq k 0) true (od (pred k))))
+            (let q 1)
+            (def od (k) (if (eq k 0) false (ev (sub k q))))
+            (ev n))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(is_even 10)",
+        "(is_odd 7)",
+        "(is_even 100000)",
+        "(uses_forward 1)",
+        "(parity 9)",
+        "(parity 100000)",
+        "(count_down 10)",
+        "(split_parity 7)",
+    ];
+
+    let expected = vec!["true", "true", "true", "2", "false", "true", "5", "false"];
+
+    compare_many(exprs, expected, &mut ctx);
+
+    // tail calls between them don't grow the stack
+    let depth = nova::evaluator::limits::EvalLimits {
+        max_depth: Some(20),
+        ..Default::default()
+    };
+    let mut ctx = ctx.with_limits(depth);
+    compare("(is_odd 10001)", "true", &mut ctx);
+    compare("(parity 10000)", "true", &mut ctx);
+}
+
+#[test]
+pub fn lazy_eval_test() {
+    use nova::evaluator::context_tco::Strategy;
+
+    let defs = vec![
+        "(def my_if (c a b) (if c a b))",
+        "(def loop (n) (loop (succ n)))",
+        "(def const_one (x) 1)",
+        "(def dbl (x) (add x x))",
+        "(def nest (n x) (if (eq n 0) x (nest (pred n) (dbl x))))",
+        "(def apply_to (f x) (f x))",
+        // cons defers its tail, so these lists are infinite
+        "(def ones (u) (cons 1 (ones u)))",
+        "(def nats (n) (cons n (nats (succ n))))",
+        "(def take (n lst) (if (eq n 0) [] (cons (car lst) (take (pred n) (cdr lst)))))",
+        "(def sum (lst) (match lst ([] 0) ((cons h t) (add h (sum t)))))",
+    ];
+
+    let mut ctx = EvalContext::new().with_strategy(Strategy::Lazy);
+    for def in defs.iter() {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(my_if true 1 (error \"boom\"))",
+        "(my_if false (error \"boom\") 2)",
+        "(const_one (loop 0))",
+        // each x is read twice: only linear because thunks are memoized
+        "(nest 40 1)",
+        "(apply_to (x -> (mul x 2)) (add 1 2))",
+        "(let k 5 (my_if (eq k 5) [k] (loop 0)))",
+        "(sum (take 3 (ones 0)))",
+        "(sum (take 100 (nats 1)))",
+        "(car (cdr (cdr (nats 5))))",
+        "(take 2 (ones 0))",
+        "(my_if true (error \"boom\") 2)",
+    ];
+
+    let expected = vec![
+        "1",
+        "2",
+        "1",
+        "1099511627776",
+        "6",
+        "[5]",
+        "3",
+        "5050",
+        "7",
+        "[1,...]",
+        "Error: boom",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+
+    // n is never read, so loop builds a long chain of thunks that has to be dropped
+    let steps = nova::evaluator::limits::EvalLimits {
+        max_steps: Some(100000),
+        ..Default::default()
+    };
+    let mut limited = ctx.clone().with_limits(steps);
+    let res = evaluate_all("(loop 0)", &mut limited);
+    assert!(res.is_err());
+
+    // strict by default: both branches are evaluated before the call
+    let mut ctx = EvalContext::new();
+    for def in defs.iter() {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+    compare("(my_if true 1 (error \"boom\"))", "Error: boom", &mut ctx);
+}
+
+#[test]
+pub fn adt_match_test() {
+    let mut ctx = EvalContext::new();
+    compare(
+        "(data Shape (Circle r) (Rect w h) Empty)",
+        "Shape => Circle(r) | Rect(w,h) | Empty",
+        &mut ctx,
+    );
+
+    let defs = vec![
+        "(def area (s) (match s ((Circle r) (mul 3 r r)) ((Rect w h) (mul w h)) (Empty 0)))",
+        "(def total (lst acc) (match lst ([] acc) ((cons h t) (total t (add h acc)))))",
+        "(def count (n acc) (match n (0 acc) (_ (count (pred n) (succ acc)))))",
+        "(def nats (n) (lcons n (nats (succ n))))",
+        "(def bit (n) (data Bit On Off) (match n (0 Off) (_ On)))",
+    ];
+
+    for def in defs {
+        evaluate_all(def, &mut ctx).expect("Should define function");
+    }
+
+    let exprs = vec![
+        "(area (Circle 2))",
+        "(area (Rect 2 5))",
+        "(area Empty)",
+        "(Rect 1 2)",
+        "((Rect 3) 4)",
+        "(eq (Rect 1 2) (Rect 1 2))",
+        "(eq (Circle 1) (Rect 1 1))",
+        "(total [1 2 3] 0)",
+        "(match [1 [2 3]] ([a [b c]] (add a b c)))",
+        "(match 5 (1 \"one\") (_ \"other\"))",
+        "(match 0.5 (1/2 \"half\") (_ \"other\"))",
+        "(match true (false 0) (true 1))",
+        "(match \"b\" (\"a\" 1) (\"b\" 2))",
+        "(match (nats 1) ((cons a (cons b t)) (add a b)))",
+        "(bit 0)",
+        "(match (Circle 1) (Empty 0) (x x))",
+        "(match 3 (1 0))",
+        "(try (match 3 (1 0)) (catch e (err-kind e)))",
+        "(match (Circle 1) ((Circle a b) a))",
+        "(match 5 ((Circle) \"circle\") (_ \"other\"))",
+        "(match (Rect 1 2) (Circle \"circle\") (_ \"other\"))",
+        "(Rect 1 2 3)",
+        "(data Shape (Circle 1))",
+    ];
+
+    let expected = vec![
+        "12",
+        "10",
+        "0",
+        "(Rect 1 2)",
+        "(Rect 3 4)",
+        "true",
+        "false",
+        "6",
+        "6",
+        "other",
+        "half",
+        "1",
+        "2",
+        "3",
+        "Off",
+        "(Circle 1)",
+        "Error: 'match' has no pattern for '3'.",
+        "MatchError",
+        "Error: 'Circle' has 1 fields but the pattern '(Circle a b)' has 2.",
+        "Error: 'Circle' has 1 fields but the pattern 'Circle' has 0.",
+        "Error: 'Circle' has 1 fields but the pattern 'Circle' has 0.",
+        "Error: 'Rect' expected 2 arguments but received 3.",
+        "Error: Constructors should contain only symbols but got '(Circle 1)'.",
+    ];
+
+    compare_many(exprs, expected, &mut ctx);
+
+    // match bodies are in tail position
+    let depth = nova::evaluator::limits::EvalLimits {
+        max_depth: Some(20),
+        ..Default::default()
+    };
+    let mut ctx = ctx.with_limits(depth);
+    compare("(count 10000 0)", "10000", &mut ctx);
+    compare("(total [1 2 3 4 5 6 7 8 9 10] 0)", "55", &mut ctx);
+}